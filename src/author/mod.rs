@@ -1,12 +1,18 @@
+mod search_stats;
 mod word_range_iter;
 
 use cw::{BLOCK, Crosswords, CVec, Dir, Point, Range};
 use dict::Dict;
+use rand::{Rng, SeedableRng, StdRng, XorShiftRng, thread_rng};
 use word_stats::WordStats;
 use std::cmp;
 use std::cmp::Ordering;
-use std::collections::HashSet;
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::time::Instant;
 use std::usize;
+pub use author::search_stats::SearchStats;
 use author::word_range_iter::WordRangeIter;
 
 /// A `RangeSet` represents a choice of ranges in the crosswords grid one of which must be filled
@@ -53,11 +59,70 @@ impl RangeSet {
     }
 }
 
+/// The reason why `Author::complete_cw` returned.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SearchStatus {
+    /// A grid satisfying all requirements was found.
+    Complete,
+    /// There are no more possibilities to complete the grid.
+    Exhausted,
+    /// The deadline has passed.
+    TimeUp,
+    /// The search was cancelled via the cancellation flag.
+    Cancelled,
+}
+
+/// The symmetry of the black squares in a block-style grid.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Symmetry {
+    /// The pattern looks the same after a rotation by 180 degrees.
+    Rotational,
+    /// The pattern looks the same when mirrored at the vertical axis.
+    Mirror,
+}
+
+impl Symmetry {
+    /// Returns the cell that must be a black square if the given one is one.
+    fn partner(&self, point: Point, width: usize, height: usize) -> Point {
+        let (width, height) = (width as i32, height as i32);
+        match *self {
+            Symmetry::Rotational => Point::new(width - 1 - point.x, height - 1 - point.y),
+            Symmetry::Mirror => Point::new(width - 1 - point.x, point.y),
+        }
+    }
+}
+
+/// The number of random black square patterns to try before giving up.
+const MAX_BLOCK_PATTERNS: usize = 100;
+
+/// The number of attempts to create a valid random black square pattern.
+const MAX_PATTERN_ATTEMPTS: usize = 10;
+
+/// The maximum number of clues in a single clue cell of an arrowword grid.
+const MAX_CLUES_PER_CELL: usize = 2;
+
+/// The default maximum number of nogoods that are remembered at the same time.
+pub const DEFAULT_MAX_NOGOODS: usize = 100000;
+
+/// A combination of letters that is known to make the search fail: No word matching the pattern
+/// needs to be tried in the range anymore, until the search backtracks below the given depth.
+struct Nogood {
+    /// The number of words in the grid when the nogood was found.
+    depth: usize,
+    range: Range,
+    pattern: CVec,
+}
+
 struct StackItem<'a> {
     bt_ranges: HashSet<Range>,
-    iter: WordRangeIter<'a>,
+    iter: WordRangeIter<'a, XorShiftRng>,
     range: Range,
     attempts: usize,
+    /// The clue cell that was reserved for the word.
+    clue_cell: Option<Point>,
+    /// Whether all words tried at this position before the current one were ruled out by an
+    /// exhaustive search, so that the position can yield nogoods for the one below it.
+    exhaustive: bool,
 }
 
 /// An `Author` produces crossword grids from a given set of dictionaries.
@@ -67,9 +132,23 @@ pub struct Author<'a> {
     min_crossing: usize,
     min_crossing_rel: f32,
     max_attempts: usize,
-    stats: WordStats,
+    stats: Arc<WordStats>,
     verbose: bool,
     stack: Vec<StackItem<'a>>,
+    rng: StdRng,
+    deadline: Option<Instant>,
+    cancel_flag: Option<Arc<AtomicBool>>,
+    pinned: Vec<Range>,
+    bars: HashSet<(Point, Dir)>,
+    required: Vec<CVec>,
+    symmetry: Option<Symmetry>,
+    blocks: Option<Vec<Point>>,
+    clue_cells: bool,
+    /// The nogoods for the current position and the positions below it on the stack, by depth.
+    nogoods: Vec<Nogood>,
+    max_nogoods: usize,
+    search_stats: SearchStats,
+    profiling: bool,
 }
 
 /// Replaces the `$result` with the given range set `$rs` if that has a lower estimated word count.
@@ -87,10 +166,20 @@ macro_rules! result_range_set {
 }
 
 impl<'a> Author<'a> {
-    /// Creates a new `Author` with the given initial crosswords grid and the given dictionaries.
-    pub fn new(init_cw: &Crosswords, dicts: &'a Vec<Dict>) -> Author<'a> {
+    /// Returns the word statistics for the given dictionaries. They can be shared by several
+    /// `Author`s, e. g. in different threads.
+    pub fn create_stats(dicts: &Vec<Dict>) -> Arc<WordStats> {
         let mut stats = WordStats::new(3);
-        stats.add_words(dicts.iter().flat_map(|dict| dict.all_words()));
+        for dict in dicts {
+            stats.add_dict(dict);
+        }
+        Arc::new(stats)
+    }
+
+    /// Creates a new `Author` with the given initial crosswords grid, the given dictionaries and
+    /// their statistics, created by `create_stats`. The words in the initial grid are pinned: They
+    /// will never be removed or extended.
+    pub fn new(init_cw: &Crosswords, dicts: &'a Vec<Dict>, stats: Arc<WordStats>) -> Author<'a> {
         Author {
             dicts: dicts,
             stats: stats,
@@ -100,9 +189,62 @@ impl<'a> Author<'a> {
             min_crossing_rel: 0.,
             max_attempts: usize::MAX,
             stack: Vec::new(),
+            rng: SeedableRng::from_seed(&[thread_rng().gen()][..]),
+            deadline: None,
+            cancel_flag: None,
+            pinned: init_cw.word_ranges().collect(),
+            bars: HashSet::new(),
+            required: Vec::new(),
+            symmetry: None,
+            blocks: None,
+            clue_cells: false,
+            nogoods: Vec::new(),
+            max_nogoods: DEFAULT_MAX_NOGOODS,
+            search_stats: SearchStats::default(),
+            profiling: false,
         }
     }
 
+    /// Sets whether each word needs a clue cell next to its first letter, as in an arrowword grid,
+    /// and return the modified `Author`. Clue cells stay empty and hold at most two clues.
+    pub fn with_clue_cells(mut self, clue_cells: bool) -> Author<'a> {
+        self.clue_cells = clue_cells;
+        self
+    }
+
+    /// Switches to block-style grids and return the modified `Author`: Black squares are placed
+    /// with the given symmetry, every other cell must be filled, and each maximal run of at least
+    /// two white cells must contain exactly one word.
+    pub fn with_block_style(mut self, symmetry: Symmetry) -> Author<'a> {
+        self.symmetry = Some(symmetry);
+        self
+    }
+
+    /// Sets the words that must appear in the crosswords, and return the modified `Author`. A grid
+    /// is only complete once it contains all of them. The required words are not added to the
+    /// dictionaries: To be tried out first, they should be in the first dictionary.
+    pub fn with_required_words<'b, T: Iterator<Item = &'b CVec>>(mut self, words: T)
+            -> Author<'a> {
+        self.required = words.cloned().collect();
+        self.required.sort();
+        self
+    }
+
+    /// Sets the borders that no word may cross, and return the modified `Author`. Each bar is given
+    /// as a cell and a direction: It separates the cell from its right resp. bottom neighbor.
+    pub fn with_bars<T: Iterator<Item = (Point, Dir)>>(mut self, bars: T) -> Author<'a> {
+        self.bars = bars.collect();
+        self
+    }
+
+    /// Seeds the random number generator that decides between equally promising ranges and words
+    /// with equal scores, and return the modified `Author`. With the same seed, grid and
+    /// dictionaries, the `Author` will always produce the same crosswords.
+    pub fn with_seed(mut self, seed: usize) -> Author<'a> {
+        self.rng = SeedableRng::from_seed(&[seed][..]);
+        self
+    }
+
     /// Sets the values for the minimum absolute and relative numbers of letters in each word that
     /// are required to be shared with a perpendicular word, and return the modified `Author`.
     pub fn with_min_crossing(mut self, min_crossing: usize, min_crossing_rel: f32) -> Author<'a> {
@@ -123,16 +265,46 @@ impl<'a> Author<'a> {
         self
     }
 
+    /// Sets the maximum number of nogoods - combinations of letters that made the search fail - to
+    /// remember at the same time, and return the modified `Author`. Once the limit is reached, no
+    /// new nogoods are recorded until the search backtracks. Setting this to 0 turns them off.
+    pub fn with_max_nogoods(mut self, max_nogoods: usize) -> Author<'a> {
+        self.max_nogoods = max_nogoods;
+        self
+    }
+
+    /// Sets whether to compare the estimated numbers of words matching each chosen range with the
+    /// actual numbers, and return the modified `Author`. Counting the matches slows down the
+    /// search, so this is only useful to evaluate the word statistics.
+    pub fn with_profiling(mut self, profiling: bool) -> Author<'a> {
+        self.profiling = profiling;
+        self
+    }
+
     /// Sets the verbosity mode and return the modified `Author`. If `verbose` is true, the current
-    /// status of the crosswords grid is printed every time the algorithm backtracks.
+    /// status of the crosswords grid and the numbers of words pruned and skipped as nogoods so far
+    /// are printed every time the algorithm backtracks.
     pub fn with_verbosity(mut self, verbose: bool) -> Author<'a> {
         self.verbose = verbose;
         self
     }
 
-    /// Returns the index of the dictionary containing the given word, or None if not found.
-    pub fn get_word_category(&self, word: &CVec) -> Option<usize> {
-        self.dicts.iter().position(|dict| dict.contains(word))
+    /// Sets a deadline after which the search is stopped, and return the modified `Author`.
+    pub fn with_deadline(mut self, deadline: Instant) -> Author<'a> {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Sets a flag that can be set to `true` from another thread to stop the search, and return the
+    /// modified `Author`.
+    pub fn with_cancel_flag(mut self, cancel_flag: Arc<AtomicBool>) -> Author<'a> {
+        self.cancel_flag = Some(cancel_flag);
+        self
+    }
+
+    /// Returns the statistics of the searches so far.
+    pub fn get_search_stats(&self) -> &SearchStats {
+        &self.search_stats
     }
 
     fn is_min_crossing_possible_without(&self, range: Range, filled_range: Range) -> bool {
@@ -172,8 +344,12 @@ impl<'a> Author<'a> {
     }
 
     fn wouldnt_block(&self, range: Range, point: Point) -> bool {
-        if !self.cw.both_borders(point, range.dir) || !self.cw.contains(point) {
-            return true; // Point already belongs to a word or is outside the grid.
+        if !self.cw.both_borders(point, range.dir) || !self.cw.contains(point)
+                || self.cw.is_solid(point) {
+            return true; // Point already belongs to a word, is outside the grid or stays empty.
+        }
+        if self.clue_cells && self.cw.get_clue_cell(range) == Some(point) {
+            return true; // Point will become the word's clue cell.
         }
         if self.would_isolate_empty_cluster(range, point) {
             return false;
@@ -219,10 +395,176 @@ impl<'a> Author<'a> {
         mul
     }
 
+    /// Returns `false` if a word in the given range would cross a bar or overlap a pinned word in
+    /// the same direction. In block style, only whole runs of white cells are allowed.
+    fn is_range_allowed(&self, range: Range) -> bool {
+        !self.pinned.iter().any(|r| r.dir == range.dir && r.intersects(&range))
+            && range.points().take(range.len - 1).all(|p| !self.bars.contains(&(p, range.dir)))
+            && (self.symmetry.is_none()
+                || (self.cw.contains(range.point)
+                    && self.get_run_containing(range.point, range.dir) == range))
+            && (!self.clue_cells || self.has_clue_cell_for(range))
+    }
+
+    /// Returns `true` if the word in the given range would have a clue cell with room for its
+    /// clue.
+    fn has_clue_cell_for(&self, range: Range) -> bool {
+        match self.cw.get_clue_cell(range) {
+            None => false,
+            Some(p) => {
+                !self.cw.is_solid(p) || self.cw.get_clued_ranges(p).len() < MAX_CLUES_PER_CELL
+            }
+        }
+    }
+
+    /// Returns the maximal range of white cells containing the given point.
+    fn get_run_containing(&self, mut point: Point, dir: Dir) -> Range {
+        let dp = dir.point();
+        while self.cw.contains(point - dp) && !self.cw.is_solid(point - dp) {
+            point = point - dp;
+        }
+        Range::cells_with(point, dir, |p| self.cw.contains(p) && !self.cw.is_solid(p))
+    }
+
+    /// Returns all maximal ranges of white cells, including those of length 1.
+    fn get_runs(&self) -> Vec<Range> {
+        let mut runs = Vec::new();
+        for &dir in &[Dir::Right, Dir::Down] {
+            let dp = dir.point();
+            for y in 0..(self.cw.get_height() as i32) {
+                for x in 0..(self.cw.get_width() as i32) {
+                    let p = Point::new(x, y);
+                    if !self.cw.is_solid(p)
+                            && (!self.cw.contains(p - dp) || self.cw.is_solid(p - dp)) {
+                        runs.push(self.get_run_containing(p, dir));
+                    }
+                }
+            }
+        }
+        runs
+    }
+
+    /// Returns `true` if no word fits into a run of white cells containing the given point.
+    fn is_in_impossible_run(&self, point: Point) -> bool {
+        [Dir::Right, Dir::Down].iter().map(|&dir| self.get_run_containing(point, dir))
+            .any(|run| self.stats.estimate_matches(&self.cw.chars(run).collect()) == 0.)
+    }
+
+    /// Returns `true` if all white cells are connected and every run of white cells is long enough
+    /// for a word. If `complete`, there must also be words matching each run.
+    fn is_block_pattern_valid(&self, complete: bool) -> bool {
+        let min_len = cmp::max(2, self.stats.get_min_len());
+        let runs = self.get_runs();
+        if runs.iter().any(|run| run.len < min_len) || (complete && runs.iter()
+                .any(|&run| self.stats.estimate_matches(&self.cw.chars(run).collect()) == 0.)) {
+            return false;
+        }
+        let (width, height) = (self.cw.get_width(), self.cw.get_height());
+        let white_count = runs.iter().filter(|run| run.dir == Dir::Right).map(|run| run.len)
+            .fold(0, |sum, len| sum + len);
+        let start = match runs.first() {
+            Some(run) => run.point,
+            None => return false,
+        };
+        let mut visited = vec!(false; width * height);
+        visited[start.coord(width, height).unwrap()] = true;
+        let mut queue = VecDeque::new();
+        queue.push_back(start);
+        let mut visited_count = 1;
+        while let Some(p) = queue.pop_front() {
+            for &dp in &[Point::new(1, 0), Point::new(-1, 0), Point::new(0, 1), Point::new(0, -1)] {
+                if let Some(i) = (p + dp).coord(width, height) {
+                    if !visited[i] && !self.cw.is_solid(p + dp) {
+                        visited[i] = true;
+                        visited_count += 1;
+                        queue.push_back(p + dp);
+                    }
+                }
+            }
+        }
+        visited_count == white_count
+    }
+
+    /// Turns the cell and its symmetric partner into black squares and returns `true`, if both
+    /// are empty and the pattern stays valid.
+    fn try_block(&mut self, point: Point, symmetry: Symmetry) -> bool {
+        let (width, height) = (self.cw.get_width(), self.cw.get_height());
+        let partner = symmetry.partner(point, width, height);
+        if self.cw.get_char(point) != Some(BLOCK) || self.cw.get_char(partner) != Some(BLOCK) {
+            return false;
+        }
+        self.cw.set_solid(point, true);
+        self.cw.set_solid(partner, true);
+        if !self.is_block_pattern_valid(false) {
+            self.cw.set_solid(point, false);
+            self.cw.set_solid(partner, false);
+            return false;
+        }
+        let blocks = self.blocks.get_or_insert(Vec::new());
+        blocks.push(point);
+        if partner != point {
+            blocks.push(partner);
+        }
+        true
+    }
+
+    /// Removes the black squares placed by `create_block_pattern`.
+    fn clear_block_pattern(&mut self) {
+        for p in self.blocks.take().unwrap_or(Vec::new()) {
+            self.cw.set_solid(p, false);
+        }
+    }
+
+    /// Randomly places black squares with the given symmetry, so that about one sixth of the grid
+    /// is black and every run of white cells can take a word. Returns `false` if no valid pattern
+    /// was found or the search was stopped.
+    fn create_block_pattern(&mut self, symmetry: Symmetry) -> bool {
+        let (width, height) = (self.cw.get_width(), self.cw.get_height());
+        let target_count = width * height / 6;
+        let mut points: Vec<Point> = (0..(height as i32))
+            .flat_map(|y| (0..(width as i32)).map(move |x| Point::new(x, y))).collect();
+        for _ in 0..MAX_PATTERN_ATTEMPTS {
+            if self.get_stop_status().is_some() {
+                return false;
+            }
+            self.blocks = Some(Vec::new());
+            self.rng.shuffle(&mut points[..]);
+            for &p in &points {
+                // Once there are enough black squares, only break up runs that are too long.
+                if self.blocks.as_ref().map_or(0, Vec::len) < target_count
+                        || self.is_in_impossible_run(p) {
+                    self.try_block(p, symmetry);
+                }
+            }
+            if self.is_block_pattern_valid(true) {
+                return true;
+            }
+            self.clear_block_pattern();
+        }
+        false
+    }
+
+    /// Returns a range set with the unfilled run of white cells with the lowest estimated number
+    /// of matching words, or `None` if all of them are filled.
+    fn get_block_range_set(&self) -> Option<RangeSet> {
+        let mut result = None;
+        // A run contains a word iff there is no border after its first cell.
+        for run in self.get_runs().into_iter()
+                .filter(|run| run.len > 1 && self.cw.get_border(run.point, run.dir)) {
+            let mut rs = RangeSet::new();
+            rs.est = self.stats.estimate_matches(&self.cw.chars(run).collect());
+            rs.ranges.insert(run);
+            rs.backtrack_ranges.insert(run);
+            result_range_set!(result, rs);
+        }
+        result
+    }
+
     fn add_range(&self, rs: &mut RangeSet, range: Range) {
         let p = range.point;
         let dp = range.dir.point();
-        if self.wouldnt_block(range, p - dp)
+        if self.is_range_allowed(range)
+                && self.wouldnt_block(range, p - dp)
                 && self.wouldnt_block(range, p + dp * range.len)
                 && self.is_min_crossing_possible_without(self.cw.get_range_before(&range), range)
                 && self.is_min_crossing_possible_without(self.cw.get_range_after(&range), range) {
@@ -287,6 +629,77 @@ impl<'a> Author<'a> {
         result
     }
 
+    /// Returns `true` if a word from one of the dictionaries matches the pattern.
+    fn has_match(&self, pattern: CVec) -> bool {
+        self.dicts.iter().any(|dict| dict.matching_words(pattern.clone()).next().is_some())
+    }
+
+    /// Returns `true` if a word in the given direction containing the point could still be added.
+    /// Stops at the first free subrange with a matching word.
+    fn can_cross(&self, point: Point, dir: Dir) -> bool {
+        let range = self.cw.get_free_range_containing(point, dir);
+        let dp = dir.point();
+        let t = (point.x - range.point.x + point.y - range.point.y) as usize;
+        (0..(t + 1)).flat_map(|i| (cmp::max(t, i + 1)..range.len).map(move |j| (i, j)))
+            .map(|(i, j)| Range { point: range.point + dp * i, dir: dir, len: j - i + 1 })
+            .any(|r| {
+                self.cw.is_range_free(r) && self.is_range_allowed(r)
+                    && self.has_match(self.cw.chars(r).collect())
+            })
+    }
+
+    /// Returns the backtrack ranges if the word in the given range can't get enough crossing
+    /// words anymore, because there is no matching word for too many of its letters.
+    fn get_dead_word(&self, range: Range) -> Option<HashSet<Range>> {
+        let odir = range.dir.other();
+        let candidate_points: Vec<Point> = range.points().filter(|&p| {
+            self.cw.both_borders(p, odir)
+        }).collect();
+        let mnc = self.get_max_noncrossing(range.len);
+        if candidate_points.len() <= mnc {
+            return None;
+        }
+        let required = candidate_points.len() - mnc;
+        // Stop as soon as enough letters can be crossed.
+        let possible = candidate_points.iter().filter(|&&p| self.can_cross(p, odir))
+            .take(required).count();
+        if possible >= required {
+            return None;
+        }
+        let mut bt_ranges: HashSet<Range> = candidate_points.into_iter()
+            .map(|p| self.cw.get_free_range_containing(p, odir)).collect();
+        bt_ranges.insert(range);
+        Some(bt_ranges)
+    }
+
+    /// Checks the perpendicular ranges touched by the word that was just placed in the given
+    /// range, and returns their backtrack ranges if one of them can't be completed anymore.
+    ///
+    /// In block style, each unfilled run crossing the word must still have a matching word. In
+    /// free-form grids, the word itself and each parallel word sharing a free perpendicular range
+    /// with it must still have enough letters that a crossing word can be added to.
+    fn get_dead_crossings(&self, range: Range) -> Option<HashSet<Range>> {
+        let odir = range.dir.other();
+        if self.symmetry.is_some() {
+            return range.points().map(|p| self.get_run_containing(p, odir))
+                .filter(|run| run.len > 1 && self.cw.get_border(run.point, run.dir))
+                .find(|&run| !self.has_match(self.cw.chars(run).collect()))
+                .map(|run| vec!(run).into_iter().collect());
+        }
+        let mut words = vec!(range);
+        for p in range.points().filter(|&p| self.cw.both_borders(p, odir)) {
+            for q in self.cw.get_free_range_containing(p, odir).points() {
+                if q != p && self.cw.is_letter(q) && !self.cw.both_borders(q, range.dir) {
+                    let word = self.cw.get_word_range_containing(q, range.dir);
+                    if !words.contains(&word) {
+                        words.push(word);
+                    }
+                }
+            }
+        }
+        words.into_iter().filter_map(|word| self.get_dead_word(word)).next()
+    }
+
     fn get_range_len_penalty(range: Range) -> i32 {
         match range.len {
             1 => 10,
@@ -301,19 +714,84 @@ impl<'a> Author<'a> {
             - Author::get_range_len_penalty(self.cw.get_range_after(range))
     }
 
+    /// Returns the ranges starting at the first empty cell where a word can begin. With clue
+    /// cells, that cell can also hold the clue for a word starting next to it.
     fn get_ranges_for_empty(&self) -> RangeSet {
         let mut result = RangeSet::new();
-        let point = Point::new(0, 0);
-        for len in (2..(1 + self.cw.get_width())) {
-            self.add_range(&mut result, Range { point: point, dir: Dir::Right, len: len });
-        }
-        for len in (2..(1 + self.cw.get_height())) {
-            self.add_range(&mut result, Range { point: point, dir: Dir::Down, len: len });
+        let (width, height) = (self.cw.get_width(), self.cw.get_height());
+        for point in (0..(width * height))
+                .map(|i| Point::new((i % width) as i32, (i / width) as i32))
+                .filter(|&p| self.cw.get_char(p) == Some(BLOCK)) {
+            let mut starts = vec!(point);
+            if self.clue_cells {
+                starts.extend(vec!(point + Point::new(1, 0), point + Point::new(0, 1)).into_iter()
+                              .filter(|&p| self.cw.get_char(p) == Some(BLOCK)));
+            }
+            for start in starts {
+                for len in (2..(1 + width - start.x as usize)) {
+                    self.add_range(&mut result, Range { point: start, dir: Dir::Right, len: len });
+                }
+                for len in (2..(1 + height - start.y as usize)) {
+                    self.add_range(&mut result, Range { point: start, dir: Dir::Down, len: len });
+                }
+            }
+            if !result.ranges.is_empty() {
+                break;
+            }
         }
         result
     }
 
+    /// Returns the required words that are not in the grid yet.
+    fn get_missing_words(&self) -> Vec<&CVec> {
+        self.required.iter().filter(|word| !self.cw.get_words().contains(*word)).collect()
+    }
+
+    /// Returns `true` if the word could be placed somewhere in the current grid. Only positions
+    /// within the grid whose letters match the word are checked further.
+    fn can_fit(&self, word: &CVec) -> bool {
+        let (width, height) = (self.cw.get_width() as i32, self.cw.get_height() as i32);
+        let len = word.len() as i32;
+        for &dir in &[Dir::Right, Dir::Down] {
+            let dp = dir.point();
+            for y in 0..(height - dp.y * (len - 1)) {
+                for x in 0..(width - dp.x * (len - 1)) {
+                    let range = Range { point: Point::new(x, y), dir: dir, len: word.len() };
+                    if self.cw.chars(range).zip(word.iter()).all(|(c, &wc)| c == wc || c == BLOCK)
+                            && self.cw.is_word_allowed(range.point, dir, word)
+                            && self.is_range_allowed(range) {
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    /// Returns `true` if a missing required word matches the given pattern.
+    fn fits_missing_word(&self, pattern: &CVec) -> bool {
+        self.get_missing_words().into_iter().any(|word| word.len() == pattern.len()
+            && word.iter().zip(pattern.iter()).all(|(&c, &p)| c == p || p == BLOCK))
+    }
+
+    /// Returns a set of ranges one of which must be filled to satisfy the requirements, or `None`
+    /// if the crosswords is complete. If it cannot be completed anymore, the set is empty.
     fn get_range_set(&self) -> Option<RangeSet> {
+        let missing_words = self.get_missing_words();
+        if missing_words.iter().any(|word| !self.can_fit(word)) {
+            return Some(RangeSet::new()); // A required word doesn't fit anymore.
+        }
+        let result = match self.symmetry {
+            None => self.get_grid_range_set(),
+            Some(_) => self.get_block_range_set(),
+        };
+        match result {
+            None if !missing_words.is_empty() => Some(RangeSet::new()),
+            result => result,
+        }
+    }
+
+    fn get_grid_range_set(&self) -> Option<RangeSet> {
         if self.cw.is_empty() {
             return Some(self.get_ranges_for_empty());
         }
@@ -322,7 +800,9 @@ impl<'a> Author<'a> {
             return result;
         }
         let mut rs = RangeSet::new();
-        for (p0, p1) in self.cw.get_smallest_boundary() {
+        let mut boundary: Vec<_> = self.cw.get_smallest_boundary().into_iter().collect();
+        boundary.sort_by_key(|&(p0, p1)| (p0.y, p0.x, p1.y, p1.x));
+        for (p0, p1) in boundary {
             let dir = if p0.y == p1.y { Dir::Right } else { Dir::Down };
             let p_ranges = match self.get_all_ranges(p0, dir, &result) {
                 Some(r) => r,
@@ -336,37 +816,118 @@ impl<'a> Author<'a> {
             }
             rs.backtrack_ranges.extend(p_ranges.backtrack_ranges.into_iter());
         }
+        if self.clue_cells && rs.ranges.is_empty() {
+            return result; // The empty cells can't take any letters, but can hold clues.
+        }
         result_range_set!(result, rs);
         result
     }
 
-    fn get_sorted_ranges(&self, range_set: HashSet<Range>) -> Vec<(Range, CVec)> {
+    /// Returns the ranges with their current contents, sorted by descending score. Ranges with
+    /// equal scores are put in random order.
+    fn get_sorted_ranges(&mut self, range_set: HashSet<Range>) -> Vec<(Range, CVec)> {
         let mut ranges: Vec<(Range, CVec)> = range_set.into_iter()
             .map(|range| (range, self.cw.chars(range).collect())).collect();
+        // Bring the ranges into a well-defined order first, so that the result only depends on
+        // the seed.
+        ranges.sort_by_key(|&(r, _)| (r.point.y, r.point.x, r.dir == Dir::Down, r.len));
+        self.rng.shuffle(&mut ranges[..]);
         ranges.sort_by(|r0, r1| self.range_score(&r1.0).cmp(&self.range_score(&r0.0)));
+        if !self.required.is_empty() {
+            // Try the ranges first in which a missing required word would fit.
+            ranges.sort_by_key(|&(_, ref pattern)| !self.fits_missing_word(pattern));
+        }
         ranges
     }
 
-    fn pop(&mut self) -> Option<StackItem<'a>> {
-        let opt_item = self.stack.pop();
-        if let Some(ref item) = opt_item {
-            let range = item.range;
-            if self.verbose {
-                println!("{}", &self.cw);
-                println!("Popping {} at ({}, {}) {:?}",
-                         self.cw.chars(range).collect::<String>(),
-                         range.point.x, range.point.y, range.dir);
+    /// Returns the result of `get_range_set` and records the time it took. If profiling, also
+    /// records the estimated and actual numbers of words matching each of the ranges.
+    fn choose_range_set(&mut self) -> Option<RangeSet> {
+        let start = Instant::now();
+        let result = self.get_range_set();
+        self.search_stats.range_set_time += start.elapsed();
+        if let Some(ref rs) = result {
+            self.search_stats.nodes += 1;
+            if self.profiling {
+                for &range in &rs.ranges {
+                    let pattern: CVec = self.cw.chars(range).collect();
+                    let actual: usize = self.dicts.iter()
+                        .map(|dict| dict.matching_words(pattern.clone()).count()).sum();
+                    let est = self.stats.estimate_matches(&pattern);
+                    self.search_stats.add_estimate(est, actual);
+                }
             }
-            self.cw.pop_word(range.point, range.dir);
         }
-        opt_item
+        result
+    }
+
+    /// Removes the topmost word and returns it with its stack item. The nogoods found after it
+    /// was placed are forgotten.
+    fn pop(&mut self) -> Option<(StackItem<'a>, CVec)> {
+        let item = match self.stack.pop() {
+            Some(item) => item,
+            None => return None,
+        };
+        let range = item.range;
+        if self.verbose {
+            println!("{}", &self.cw);
+            println!("Popping {} at ({}, {}) {:?}; pruned so far: {}, nogood hits: {}",
+                     self.cw.chars(range).collect::<String>(),
+                     range.point.x, range.point.y, range.dir,
+                     self.search_stats.pruned, self.search_stats.nogood_hits);
+        }
+        let word = self.cw.pop_word(range.point, range.dir);
+        if let Some(p) = item.clue_cell {
+            self.cw.set_solid(p, false);
+        }
+        let depth = self.stack.len();
+        while self.nogoods.last().map_or(false, |nogood| nogood.depth > depth) {
+            self.nogoods.pop();
+        }
+        Some((item, word))
     }
 
-    /// Pops the stack until there are no more than n words left in the grid.
-    pub fn pop_to_n_words(&mut self, n: usize) {
-        while self.stack.len() > n {
-            self.pop();
+    /// Returns the pattern of the letters of the word in the given range that are in or next to
+    /// one of the backtrack ranges. If the word made the search fail, these letters alone are to
+    /// blame, and every other word matching the pattern would fail, too. Returns `None` if none of
+    /// these letters were placed with the word, i. e. the word's range is still empty there, or if
+    /// all of them are to blame, so that no other word could match the pattern.
+    fn get_nogood_pattern(&self, range: Range, word: &CVec, bt_ranges: &HashSet<Range>)
+            -> Option<CVec> {
+        if bt_ranges.is_empty() {
+            return None;
         }
+        let mut new_letter = false;
+        let mut blank = false;
+        let pattern = range.points().zip(word.iter()).map(|(p, &c)| {
+            if self.cw.get_char(p) != Some(BLOCK) {
+                c // The letter was there before the word.
+            } else if bt_ranges.iter().any(|r| r.points()
+                    .any(|q| (p.x - q.x).abs() + (p.y - q.y).abs() <= 1)) {
+                new_letter = true;
+                c
+            } else {
+                blank = true;
+                BLOCK
+            }
+        }).collect();
+        if new_letter && blank { Some(pattern) } else { None }
+    }
+
+    /// Records that no word matching the pattern needs to be tried in the range at the current
+    /// depth, unless the maximum number of nogoods is reached.
+    fn add_nogood(&mut self, range: Range, pattern: CVec) {
+        if self.nogoods.len() < self.max_nogoods {
+            self.nogoods.push(Nogood { depth: self.stack.len(), range: range, pattern: pattern });
+        }
+    }
+
+    /// Returns `true` if the word matches a nogood for the range at the current depth.
+    fn is_nogood(&self, range: Range, word: &CVec) -> bool {
+        let depth = self.stack.len();
+        self.nogoods.iter().rev().take_while(|nogood| nogood.depth == depth)
+            .any(|nogood| nogood.range == range && nogood.pattern.iter().zip(word.iter())
+                .all(|(&p, &c)| p == BLOCK || p == c))
     }
 
     fn range_meets(range: &Range, bt_ranges: &HashSet<Range>) -> bool {
@@ -374,52 +935,420 @@ impl<'a> Author<'a> {
             || bt_ranges.iter().any(|r| range.intersects(r) || range.is_adjacent_to(r))
     }
 
-    pub fn complete_cw(&mut self) -> Option<Crosswords> {
+    /// Returns the reason to stop the search, if the search was cancelled or the time is up.
+    fn get_stop_status(&self) -> Option<SearchStatus> {
+        if self.cancel_flag.iter().any(|flag| flag.load(AtomicOrdering::Relaxed)) {
+            Some(SearchStatus::Cancelled)
+        } else if self.deadline.iter().any(|&deadline| Instant::now() >= deadline) {
+            Some(SearchStatus::TimeUp)
+        } else {
+            None
+        }
+    }
+
+    /// Searches for the next grid that satisfies all requirements and returns it, together with
+    /// `SearchStatus::Complete`. If no such grid was found - because there are no more
+    /// possibilities, the deadline has passed or the search was cancelled - returns the reason
+    /// and the grid with the most letters encountered during the search.
+    ///
+    /// After an interrupted search, calling this again will continue the search, but may skip
+    /// some of the possibilities.
+    ///
+    /// In block style, if a pattern of black squares cannot be filled, a new one is created.
+    pub fn complete_cw(&mut self) -> (SearchStatus, Crosswords) {
+        let symmetry = match self.symmetry {
+            None => return self.fill_cw(),
+            Some(symmetry) => symmetry,
+        };
+        let mut best_partial = self.cw.clone();
+        for _ in 0..MAX_BLOCK_PATTERNS {
+            if self.blocks.is_none() && !self.create_block_pattern(symmetry) {
+                if self.get_stop_status().is_some() {
+                    break;
+                }
+                continue; // Count it as a failed pattern and start over.
+            }
+            let (status, cw) = self.fill_cw();
+            if status != SearchStatus::Exhausted {
+                return (status, cw);
+            }
+            if cw.count_letters() > best_partial.count_letters() {
+                best_partial = cw;
+            }
+            self.clear_block_pattern();
+            if let Some(status) = self.get_stop_status() {
+                return (status, best_partial);
+            }
+        }
+        (self.get_stop_status().unwrap_or(SearchStatus::Exhausted), best_partial)
+    }
+
+    fn fill_cw(&mut self) -> (SearchStatus, Crosswords) {
+        let mut best_partial = self.cw.clone();
+        let mut best_letters = self.cw.count_letters();
         let mut bt_ranges = HashSet::new();
         let mut attempts = 0;
+        // A grid was found or the search was interrupted after the words on the stack were placed:
+        // Their positions won't yield nogoods anymore.
+        for item in &mut self.stack {
+            item.exhaustive = false;
+        }
+        let mut exhaustive = self.stack.is_empty();
         let mut iter = match self.pop() {
-            Some(item) => item.iter, // Drop bt_ranges, as iter was successful!.
-            None => match self.get_range_set() {
-                Some(rs) => WordRangeIter::new(self.get_sorted_ranges(rs.ranges), self.dicts),
-                None => return None,
+            Some((item, _)) => item.iter, // Drop bt_ranges, as iter was successful!.
+            None => match self.choose_range_set() {
+                Some(rs) => WordRangeIter::new(self.get_sorted_ranges(rs.ranges), self.dicts,
+                                              self.rng.gen()),
+                None => return (SearchStatus::Exhausted, best_partial),
             },
         };
         'main: loop {
             while let Some((range, word)) = iter.next() {
+                self.search_stats.words_tried += 1;
+                if let Some(status) = self.get_stop_status() {
+                    return (status, best_partial);
+                }
+                if self.is_nogood(range, &word) {
+                    self.search_stats.nogood_hits += 1;
+                    continue;
+                }
                 if self.cw.try_word(range.point, range.dir, &word) {
+                    if let Some(dead_ranges) = self.get_dead_crossings(range) {
+                        self.cw.pop_word(range.point, range.dir);
+                        self.search_stats.pruned += 1;
+                        if let Some(pattern) = self.get_nogood_pattern(range, &word, &dead_ranges) {
+                            self.add_nogood(range, pattern);
+                        }
+                        bt_ranges.extend(dead_ranges);
+                        continue;
+                    }
+                    let clue_cell = match self.cw.get_clue_cell(range) {
+                        Some(p) if self.clue_cells && !self.cw.is_solid(p) => {
+                            self.cw.set_solid(p, true);
+                            Some(p)
+                        }
+                        _ => None,
+                    };
+                    let letters = self.cw.count_letters();
+                    if letters > best_letters {
+                        best_letters = letters;
+                        best_partial = self.cw.clone();
+                    }
                     self.stack.push(StackItem {
                         bt_ranges: bt_ranges,
                         range: range,
                         iter: iter,
                         attempts: attempts + 1,
+                        clue_cell: clue_cell,
+                        exhaustive: exhaustive,
                     });
-                    match self.get_range_set() {
+                    exhaustive = true;
+                    self.search_stats.words_placed += 1;
+                    self.search_stats.max_depth = cmp::max(self.search_stats.max_depth,
+                                                           self.stack.len());
+                    match self.choose_range_set() {
                         Some(rs) => {
+                            // Without backtrack ranges, e. g. if a required word doesn't fit
+                            // anymore, the failure can't be blamed on any particular letters.
+                            if rs.backtrack_ranges.is_empty() {
+                                exhaustive = false;
+                            }
                             bt_ranges = rs.backtrack_ranges;
                             iter = WordRangeIter::new(self.get_sorted_ranges(rs.ranges),
-                                                      self.dicts);
+                                                      self.dicts, self.rng.gen());
                             attempts = 0;
                         }
-                        None => return Some(self.cw.clone()),
+                        None => return (SearchStatus::Complete, self.cw.clone()),
                     };
+                } else if self.cw.get_words().contains(&word) {
+                    // The word is already used elsewhere in the grid, so this failure can't be
+                    // blamed on particular letters either.
+                    exhaustive = false;
                 }
             }
-            while let Some(item) = self.pop() {
-                // TODO: Remember which characters not to try again.
-                // TODO: Save the current range set as a "try next" hint. (Is there a way to make
-                //       that work recursively ...?)
+            while let Some((item, word)) = self.pop() {
+                self.search_stats.backtracks += 1;
                 if Author::range_meets(&item.range, &bt_ranges)
                         && (item.attempts < self.max_attempts || self.stack.len() == 0) {
+                    // Only a word whose consequences were all tried out is proven to fail.
+                    if exhaustive {
+                        if let Some(pattern) = self.get_nogood_pattern(item.range, &word,
+                                                                       &bt_ranges) {
+                            self.add_nogood(item.range, pattern);
+                        }
+                    }
+                    exhaustive = exhaustive && item.exhaustive;
                     bt_ranges.extend(item.bt_ranges);
                     iter = item.iter;
                     attempts = item.attempts;
                     continue 'main;
                 }
+                exhaustive = false; // The remaining words at that position were skipped.
             }
             // Went all up the stack but found nothing? Give up.
-            return None;
-            // TODO: If time is up or user interrupts, break.
+            self.nogoods.clear();
+            return (SearchStatus::Exhausted, best_partial);
         }
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cw::{Crosswords, Dir, Point, Range};
+    use dict::Dict;
+    use dict::tests::random_words;
+    use rand::thread_rng;
+    use std::collections::HashSet;
+    use std::env;
+    use std::fs::File;
+    use std::io::{BufRead, BufReader};
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicBool;
+    use std::time::Duration;
+
+    fn create_dicts() -> Vec<Dict> {
+        let words_vec = vec!("AB", "CD", "AC", "BD");
+        let words = Dict::to_cvec_set(words_vec.into_iter().map(|s| s.to_string()));
+        vec!(Dict::new(words.iter(), &mut thread_rng()))
+    }
+
+    #[test]
+    fn test_complete() {
+        let dicts = create_dicts();
+        let mut author = Author::new(&Crosswords::new(2, 2), &dicts, Author::create_stats(&dicts));
+        let (status, cw) = author.complete_cw();
+        assert_eq!(SearchStatus::Complete, status);
+        assert_eq!(4, cw.get_words().len());
+    }
+
+    #[test]
+    fn test_search_stats() {
+        let dicts = create_dicts();
+        let mut author = Author::new(&Crosswords::new(2, 2), &dicts, Author::create_stats(&dicts))
+            .with_profiling(true);
+        assert_eq!(SearchStatus::Complete, author.complete_cw().0);
+        let stats = author.get_search_stats();
+        assert_eq!(4, stats.max_depth);
+        assert!(stats.words_placed >= 4 && stats.words_tried >= stats.words_placed);
+        assert!(stats.nodes >= 4 && stats.estimates > 0);
+        assert!(stats.actual_matches > 0);
+        assert_eq!(0, stats.backtracks);
+        // Resuming removes the last word, but that doesn't count as backtracking.
+        assert_eq!(SearchStatus::Complete, author.complete_cw().0);
+        let stats = author.get_search_stats();
+        assert_eq!(stats.words_placed - 4 - 1, stats.backtracks);
+    }
+
+    #[test]
+    fn test_required_words() {
+        let dicts = create_dicts();
+        let required = vec!("CD".chars().collect());
+        let mut author = Author::new(&Crosswords::new(2, 2), &dicts, Author::create_stats(&dicts))
+            .with_required_words(required.iter());
+        let (status, cw) = author.complete_cw();
+        assert_eq!(SearchStatus::Complete, status);
+        assert!(cw.get_words().contains(&required[0]));
+        let required = vec!("ABC".chars().collect());
+        let mut author = Author::new(&Crosswords::new(2, 2), &dicts, Author::create_stats(&dicts))
+            .with_required_words(required.iter());
+        assert_eq!(SearchStatus::Exhausted, author.complete_cw().0);
+    }
+
+    #[test]
+    fn test_block_style() {
+        let words_vec = vec!("ABC", "DEF", "GHI", "ADG", "BEH", "CFI");
+        let words = Dict::to_cvec_set(words_vec.into_iter().map(|s| s.to_string()));
+        let dicts = vec!(Dict::new(words.iter(), &mut thread_rng()));
+        let mut author = Author::new(&Crosswords::new(3, 3), &dicts, Author::create_stats(&dicts))
+            .with_block_style(Symmetry::Rotational);
+        let (status, cw) = author.complete_cw();
+        assert_eq!(SearchStatus::Complete, status);
+        assert_eq!(6, cw.get_words().len());
+        assert!(cw.is_full());
+    }
+
+    #[test]
+    fn test_dead_crossings() {
+        let top = Range { point: Point::new(0, 0), dir: Dir::Right, len: 3 };
+        let to_dicts = |words: Vec<&str>| {
+            let words = Dict::to_cvec_set(words.into_iter().map(|s| s.to_string()));
+            vec!(Dict::new(words.iter(), &mut thread_rng()))
+        };
+        // In block style, no word starts with the D in the last column.
+        let dicts = to_dicts(vec!("ABC", "BAD"));
+        let mut author = Author::new(&Crosswords::new(3, 3), &dicts, Author::create_stats(&dicts))
+            .with_block_style(Symmetry::Rotational);
+        assert!(author.cw.try_word(top.point, top.dir, &"BAD".chars().collect()));
+        let dead = Range { point: Point::new(2, 0), dir: Dir::Down, len: 3 };
+        assert_eq!(Some(vec!(dead).into_iter().collect()), author.get_dead_crossings(top));
+        author.cw.pop_word(top.point, top.dir);
+        assert!(author.cw.try_word(top.point, top.dir, &"ABC".chars().collect()));
+        assert!(author.get_dead_crossings(top).is_some());
+        // In a free-form grid, two of the three letters need a crossing word.
+        let dicts = to_dicts(vec!("ABC", "AX", "BY"));
+        let mut author = Author::new(&Crosswords::new(3, 2), &dicts, Author::create_stats(&dicts));
+        assert!(author.cw.try_word(top.point, top.dir, &"ABC".chars().collect()));
+        assert_eq!(None, author.get_dead_crossings(top));
+        let dicts = to_dicts(vec!("ABC", "AX"));
+        let mut author = Author::new(&Crosswords::new(3, 2), &dicts, Author::create_stats(&dicts));
+        assert!(author.cw.try_word(top.point, top.dir, &"ABC".chars().collect()));
+        assert!(author.get_dead_crossings(top).is_some());
+    }
+
+    #[test]
+    fn test_nogoods() {
+        let words = Dict::to_cvec_set(vec!("ABC", "BAD").into_iter().map(|s| s.to_string()));
+        let dicts = vec!(Dict::new(words.iter(), &mut thread_rng()));
+        let mut author = Author::new(&Crosswords::new(3, 3), &dicts, Author::create_stats(&dicts))
+            .with_block_style(Symmetry::Rotational);
+        let top = Range { point: Point::new(0, 0), dir: Dir::Right, len: 3 };
+        let word = "BAD".chars().collect();
+        assert!(author.cw.try_word(top.point, top.dir, &word));
+        let dead_ranges = author.get_dead_crossings(top).unwrap();
+        author.cw.pop_word(top.point, top.dir);
+        // The letters in and next to the last column are to blame.
+        let pattern = author.get_nogood_pattern(top, &word, &dead_ranges).unwrap();
+        assert_eq!("#AD", pattern.iter().cloned().collect::<String>());
+        author.add_nogood(top, pattern);
+        assert!(author.is_nogood(top, &"XAD".chars().collect()));
+        assert!(!author.is_nogood(top, &"BAX".chars().collect()));
+        assert_eq!(None, author.get_nogood_pattern(top, &word, &HashSet::new()));
+        // If all letters are to blame, the pattern only matches the word itself.
+        let own_range = vec!(top).into_iter().collect();
+        assert_eq!(None, author.get_nogood_pattern(top, &word, &own_range));
+    }
+
+    #[test]
+    fn test_can_fit() {
+        let dicts = create_dicts();
+        let mut author = Author::new(&Crosswords::new(3, 1), &dicts, Author::create_stats(&dicts));
+        let word = |s: &str| s.chars().collect::<CVec>();
+        assert!(author.can_fit(&word("ABC")));
+        assert!(!author.can_fit(&word("ABCD")));
+        assert!(author.cw.try_word(Point::new(0, 0), Dir::Right, &word("ABC")));
+        assert!(!author.can_fit(&word("ABC"))); // It is already used.
+        assert!(!author.can_fit(&word("ABD")));
+    }
+
+    #[test]
+    fn test_nogoods_with_required_word() {
+        // Whether CDA still fits depends on the whole grid, so a failure to place it must not be
+        // blamed on a few letters, which would prune the only solution.
+        let words_vec = vec!("AAB", "BAC", "BC", "BD", "BDAAD", "BDCD", "CADD", "CBA", "CDA",
+                             "DBCAD", "DCC", "DDDC");
+        let words = Dict::to_cvec_set(words_vec.into_iter().map(|s| s.to_string()));
+        let dicts = vec!(Dict::new(words.iter(), &mut thread_rng()));
+        let required = vec!("CDA".chars().collect());
+        for seed in 0..5 {
+            let mut author = Author::new(&Crosswords::new(4, 3), &dicts,
+                                         Author::create_stats(&dicts))
+                .with_required_words(required.iter())
+                .with_seed(seed);
+            let (status, cw) = author.complete_cw();
+            assert_eq!(SearchStatus::Complete, status);
+            assert!(cw.get_words().contains(&required[0]));
+        }
+    }
+
+    #[test]
+    fn test_clue_cells() {
+        let dicts = create_dicts();
+        // Only the top left cell can be neither a letter nor a clue cell.
+        let mut cw = Crosswords::new(3, 3);
+        cw.set_solid(Point::new(0, 0), true);
+        let mut author = Author::new(&cw, &dicts, Author::create_stats(&dicts))
+            .with_clue_cells(true);
+        let (status, cw) = author.complete_cw();
+        assert_eq!(SearchStatus::Complete, status);
+        for range in cw.word_ranges() {
+            let p = cw.get_clue_cell(range).unwrap();
+            assert!(cw.is_solid(p));
+            assert!(cw.get_clued_ranges(p).len() <= MAX_CLUES_PER_CELL);
+        }
+    }
+
+    #[test]
+    fn test_cancel() {
+        let dicts = create_dicts();
+        let mut author = Author::new(&Crosswords::new(2, 2), &dicts, Author::create_stats(&dicts))
+            .with_cancel_flag(Arc::new(AtomicBool::new(true)));
+        let (status, cw) = author.complete_cw();
+        assert_eq!(SearchStatus::Cancelled, status);
+        assert!(cw.is_empty());
+        // No black square patterns are generated once the time is up.
+        let mut author = Author::new(&Crosswords::new(9, 9), &dicts, Author::create_stats(&dicts))
+            .with_block_style(Symmetry::Rotational)
+            .with_deadline(Instant::now());
+        let (status, cw) = author.complete_cw();
+        assert_eq!(SearchStatus::TimeUp, status);
+        assert!(cw.is_empty());
+        assert!(author.blocks.is_none());
+    }
+
+    /// Returns a dictionary with the words from the file `CROSSWORDS_BENCH_DICT`, one per line, or
+    /// with 100000 random words if that variable is not set.
+    fn bench_dicts() -> Vec<Dict> {
+        let mut words: Vec<CVec> = match env::var("CROSSWORDS_BENCH_DICT") {
+            Ok(filename) => {
+                let file = File::open(&filename)
+                    .unwrap_or_else(|err| panic!("Can't open {}: {}", filename, err));
+                Dict::to_cvec_set(BufReader::new(file).lines().map(Result::unwrap))
+                    .into_iter().collect()
+            }
+            Err(_) => random_words(100000, 1).into_iter().collect(),
+        };
+        words.sort(); // Make the order of the dictionary independent of the hash set.
+        let mut rng: StdRng = SeedableRng::from_seed(&[1][..]);
+        vec!(Dict::new(words.iter(), &mut rng))
+    }
+
+    /// Fills empty grids of the given sizes with four seeds each and a 60 s deadline, and prints
+    /// the time it took. The function configures the `Author` for each grid.
+    fn bench_grids<F>(sizes: &[(usize, usize)], configure: F)
+            where F: for<'a> Fn(Author<'a>) -> Author<'a> {
+        let dicts = bench_dicts();
+        let mut total = Duration::from_secs(0);
+        let mut complete_count = 0;
+        for &(width, height) in sizes {
+            for seed in 0..4 {
+                let start = Instant::now();
+                let author = Author::new(&Crosswords::new(width, height), &dicts,
+                                         Author::create_stats(&dicts))
+                    .with_seed(seed)
+                    .with_deadline(start + Duration::from_secs(60));
+                let (status, cw) = configure(author).complete_cw();
+                let elapsed = start.elapsed();
+                total += elapsed;
+                println!("{}x{}, seed {}: {:?} in {:?}", width, height, seed, status, elapsed);
+                assert!(status != SearchStatus::Cancelled);
+                if status == SearchStatus::Complete {
+                    complete_count += 1;
+                    assert!(cw.get_words().iter().all(|word| dicts[0].contains(word)));
+                }
+            }
+        }
+        println!("{} words, {} complete, total: {:?}", dicts[0].all_words().count(),
+                 complete_count, total);
+    }
+
+    /// Measures the time it takes to fill empty block-style grids. Run with
+    /// `cargo test --release bench_complete_cw -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_complete_cw() {
+        bench_grids(&[(9, 9), (11, 11), (13, 13), (15, 15)],
+                    |author| author.with_block_style(Symmetry::Rotational));
+    }
+
+    /// Measures the time it takes to fill empty free-form grids, where every word must be crossed
+    /// by at least two others and at least 60% of its letters. Run with
+    /// `cargo test --release bench_complete_free_form_cw -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_complete_free_form_cw() {
+        bench_grids(&[(15, 10), (20, 15), (25, 20), (30, 25)],
+                    |author| author.with_min_crossing(2, 0.6));
+    }
+}
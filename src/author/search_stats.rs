@@ -0,0 +1,134 @@
+use rustc_serialize::json::{Json, ToJson};
+use std::cmp;
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::time::Duration;
+
+/// Counters describing the course of a search, to compare heuristics and dictionaries.
+#[derive(Clone, Debug, Default)]
+pub struct SearchStats {
+    /// The number of range sets whose words were tried out.
+    pub nodes: usize,
+    /// The number of words taken from the dictionaries to be tried out in a range.
+    pub words_tried: usize,
+    /// The number of words that were placed and kept at least until the next range set.
+    pub words_placed: usize,
+    /// The number of words that were removed right after placing them, because a perpendicular
+    /// range couldn't be completed anymore.
+    pub pruned: usize,
+    /// The number of words that were skipped because they matched a nogood.
+    pub nogood_hits: usize,
+    /// The number of words that were removed while backtracking.
+    pub backtracks: usize,
+    /// The maximum number of words placed at the same time.
+    pub max_depth: usize,
+    /// The time spent choosing the next range set.
+    pub range_set_time: Duration,
+    /// The number of ranges whose estimated number of matching words was checked.
+    pub estimates: usize,
+    /// The sum of the estimated numbers of matching words of the checked ranges.
+    pub estimated_matches: f64,
+    /// The sum of the actual numbers of matching words of the checked ranges.
+    pub actual_matches: usize,
+    /// The sum of the absolute differences between the estimated and actual numbers.
+    pub estimate_error: f64,
+}
+
+/// Returns the duration in seconds.
+fn secs(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + duration.subsec_nanos() as f64 / 1e9
+}
+
+impl SearchStats {
+    /// Adds the counters of another search, e. g. for a different sample.
+    pub fn add(&mut self, other: &SearchStats) {
+        self.nodes += other.nodes;
+        self.words_tried += other.words_tried;
+        self.words_placed += other.words_placed;
+        self.pruned += other.pruned;
+        self.nogood_hits += other.nogood_hits;
+        self.backtracks += other.backtracks;
+        self.max_depth = cmp::max(self.max_depth, other.max_depth);
+        self.range_set_time += other.range_set_time;
+        self.estimates += other.estimates;
+        self.estimated_matches += other.estimated_matches;
+        self.actual_matches += other.actual_matches;
+        self.estimate_error += other.estimate_error;
+    }
+
+    /// Records the estimated and the actual number of words matching a range.
+    pub fn add_estimate(&mut self, estimated: f32, actual: usize) {
+        self.estimates += 1;
+        self.estimated_matches += estimated as f64;
+        self.actual_matches += actual;
+        self.estimate_error += (estimated as f64 - actual as f64).abs();
+    }
+}
+
+impl ToJson for SearchStats {
+    fn to_json(&self) -> Json {
+        let mut obj = BTreeMap::new();
+        obj.insert("nodes".to_string(), self.nodes.to_json());
+        obj.insert("words_tried".to_string(), self.words_tried.to_json());
+        obj.insert("words_placed".to_string(), self.words_placed.to_json());
+        obj.insert("pruned".to_string(), self.pruned.to_json());
+        obj.insert("nogood_hits".to_string(), self.nogood_hits.to_json());
+        obj.insert("backtracks".to_string(), self.backtracks.to_json());
+        obj.insert("max_depth".to_string(), self.max_depth.to_json());
+        obj.insert("range_set_secs".to_string(), secs(self.range_set_time).to_json());
+        obj.insert("estimates".to_string(), self.estimates.to_json());
+        obj.insert("estimated_matches".to_string(), self.estimated_matches.to_json());
+        obj.insert("actual_matches".to_string(), self.actual_matches.to_json());
+        obj.insert("estimate_error".to_string(), self.estimate_error.to_json());
+        Json::Object(obj)
+    }
+}
+
+impl Display for SearchStats {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), fmt::Error> {
+        try!(writeln!(formatter, "Range sets: {} ({:.3} s to choose them)",
+                      self.nodes, secs(self.range_set_time)));
+        try!(writeln!(formatter, "Words: {} tried, {} placed, {} pruned, {} skipped as nogoods",
+                      self.words_tried, self.words_placed, self.pruned, self.nogood_hits));
+        try!(writeln!(formatter, "Backtracks: {}, maximum depth: {}",
+                      self.backtracks, self.max_depth));
+        if self.estimates > 0 {
+            let n = self.estimates as f64;
+            try!(writeln!(formatter, "Matches per range: {:.1} estimated, {:.1} actual, \
+                                      {:.1} mean absolute error",
+                          self.estimated_matches / n, self.actual_matches as f64 / n,
+                          self.estimate_error / n));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustc_serialize::json::ToJson;
+    use std::time::Duration;
+
+    #[test]
+    fn test_add() {
+        let mut stats = SearchStats::default();
+        stats.nodes = 3;
+        stats.max_depth = 5;
+        stats.add_estimate(4., 2);
+        let mut other = SearchStats::default();
+        other.nodes = 2;
+        other.max_depth = 4;
+        other.range_set_time = Duration::from_millis(1500);
+        other.add_estimate(1., 3);
+        stats.add(&other);
+        assert_eq!(5, stats.nodes);
+        assert_eq!(5, stats.max_depth);
+        assert_eq!(2, stats.estimates);
+        assert_eq!(5, stats.actual_matches);
+        assert_eq!(4., stats.estimate_error);
+        let json = stats.to_json();
+        assert_eq!(Some(1.5), json.find("range_set_secs").and_then(|v| v.as_f64()));
+        assert_eq!(Some(5), json.find("nodes").and_then(|v| v.as_u64()));
+        assert!(stats.to_string().contains("2.5 actual"));
+    }
+}
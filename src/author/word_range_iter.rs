@@ -1,64 +1,89 @@
 use cw::{CVec, Range};
-use dict::{Dict, PatternIter};
+use dict::Dict;
+use rand::Rng;
 
 /// An iterator over all possibilities to fill one of the given ranges with a word from a set of
-/// dictionaries.
-pub struct WordRangeIter<'a> {
+/// dictionaries. The dictionaries are tried in order, and within each dictionary the words with
+/// higher scores come first. Words with equal scores are shuffled with the given random number
+/// generator, so that each search sample tries them in its own order, and for the same score, the
+/// words for earlier ranges come first.
+pub struct WordRangeIter<'a, R: Rng> {
     ranges: Vec<(Range, CVec)>,
     dicts: &'a Vec<Dict>,
-    range_i: usize,
     dict_i: usize,
-    pi: Option<PatternIter<'a>>,
+    rng: R,
+    /// For each range, the remaining matching words in the current dictionary with their scores,
+    /// in reverse order.
+    words: Vec<Vec<(&'a CVec, u32)>>,
 }
 
-impl<'a> WordRangeIter<'a> {
-    pub fn new(ranges: Vec<(Range, CVec)>, dicts: &'a Vec<Dict>) -> WordRangeIter<'a> {
+impl<'a, R: Rng> WordRangeIter<'a, R> {
+    pub fn new(ranges: Vec<(Range, CVec)>, dicts: &'a Vec<Dict>, rng: R) -> WordRangeIter<'a, R> {
         WordRangeIter {
             ranges: ranges,
             dicts: dicts,
-            range_i: 0,
             dict_i: 0,
-            pi: None,
+            rng: rng,
+            words: Vec::new(),
         }
     }
 
-    #[inline]
-    fn get_word(&mut self) -> Option<CVec> {
-        match self.pi {
-            None => None,
-            Some(ref mut iter) => iter.next().cloned(),
-        }
+    /// Collects the matching words from the current dictionary. Returns `false` if there are no
+    /// more dictionaries.
+    fn start_dict(&mut self) -> bool {
+        let dict = match self.dicts.get(self.dict_i) {
+            Some(dict) => dict,
+            None => return false,
+        };
+        let rng = &mut self.rng;
+        self.words = self.ranges.iter().map(|&(_, ref pattern)| {
+            let mut iter = dict.matching_words(pattern.clone());
+            let mut words = Vec::new();
+            while let Some(word) = iter.next_scored() {
+                words.push(word);
+            }
+            let mut start = 0;
+            while start < words.len() {
+                let score = words[start].1;
+                let end = start + words[start..].iter().take_while(|w| w.1 == score).count();
+                rng.shuffle(&mut words[start..end]);
+                start = end;
+            }
+            words.reverse();
+            words
+        }).collect();
+        true
     }
 
-    fn advance(&mut self) -> bool {
-        if self.pi.is_some() {
-            self.range_i += 1;
-            if self.range_i >= self.ranges.len() {
-                self.range_i = 0;
-                self.dict_i += 1;
+    /// Returns the index of the range whose next word has the highest score.
+    fn best_range(&self) -> Option<usize> {
+        let mut best: Option<(usize, u32)> = None;
+        for (i, words) in self.words.iter().enumerate() {
+            if let Some(&(_, score)) = words.last() {
+                if best.map_or(true, |(_, best_score)| score > best_score) {
+                    best = Some((i, score));
+                }
             }
         }
-        if let Some(&(_, ref pattern)) = self.ranges.get(self.range_i) {
-            self.pi = self.dicts.get(self.dict_i).map(|dict| dict.matching_words(pattern.clone()));
-            self.pi.is_some()
-        } else {
-            false
-        }
+        best.map(|(i, _)| i)
     }
 }
 
-impl<'a> Iterator for WordRangeIter<'a> {
+impl<'a, R: Rng> Iterator for WordRangeIter<'a, R> {
     type Item = (Range, CVec);
 
     fn next(&mut self) -> Option<(Range, CVec)> {
-        let mut oword = self.get_word();
-        while oword.is_none() && self.advance() {
-            oword = self.get_word();
+        loop {
+            if self.words.is_empty() && !self.start_dict() {
+                return None;
+            }
+            if let Some(i) = self.best_range() {
+                let word = self.words[i].pop().unwrap().0.clone();
+                return Some((self.ranges[i].0, word));
+            }
+            self.words.clear();
+            self.dict_i += 1;
         }
-        oword.map(|word| {
-            let (range, _) = self.ranges[self.range_i];
-            (range, word)
-        })
     }
 }
 
@@ -67,6 +92,7 @@ mod tests {
     use super::*;
     use cw::{Dir, Point, Range};
     use dict::Dict;
+    use rand::{SeedableRng, XorShiftRng, thread_rng};
 
     #[test]
     fn test_range_iter() {
@@ -78,15 +104,50 @@ mod tests {
         );
         let dicts = vec!(
             Dict::new(vec!("FAV".chars().collect(),
-                           "TOOLONG".chars().collect()).iter()),
+                           "TOOLONG".chars().collect()).iter(), &mut thread_rng()),
             Dict::new(vec!("YO".chars().collect(),
                            "FOO".chars().collect(),
-                           "FOOBAR".chars().collect()).iter()),
+                           "FOOBAR".chars().collect()).iter(), &mut thread_rng()),
         );
-        let mut iter = WordRangeIter::new(ranges.clone(), &dicts);
+        let mut iter = WordRangeIter::new(ranges.clone(), &dicts, thread_rng());
         assert_eq!(Some((ranges[1].0, "FAV".chars().collect())), iter.next());
         assert_eq!(Some((ranges[0].0, "FOOBAR".chars().collect())), iter.next());
         assert_eq!(Some((ranges[1].0, "FOO".chars().collect())), iter.next());
         assert_eq!(Some((ranges[2].0, "YO".chars().collect())), iter.next());
     }
+
+    #[test]
+    fn test_range_iter_scores() {
+        let point = Point::new(0, 0);
+        let ranges = vec!(
+            (Range { point: point, dir: Dir::Right, len: 3 }, "###".chars().collect()),
+            (Range { point: point, dir: Dir::Down, len: 2 }, "##".chars().collect()),
+        );
+        let words: Vec<CVec> = vec!("FOO", "BAR", "YO", "NO").into_iter()
+            .map(|s| s.chars().collect()).collect();
+        let dicts = vec!(Dict::with_scores(words.iter().zip(vec!(20, 60, 40, 20)),
+                                           &mut thread_rng()));
+        let iter = WordRangeIter::new(ranges.clone(), &dicts, thread_rng());
+        let found: Vec<String> = iter.map(|(_, word)| word.into_iter().collect()).collect();
+        assert_eq!(vec!("BAR", "YO", "FOO", "NO"), found);
+    }
+
+    #[test]
+    fn test_range_iter_order() {
+        let point = Point::new(0, 0);
+        let ranges = vec!((Range { point: point, dir: Dir::Right, len: 3 },
+                           "###".chars().collect()));
+        let words: Vec<CVec> = (0..20).map(|i| format!("A{:02}", i).chars().collect()).collect();
+        let dicts = vec!(Dict::new(words.iter(), &mut thread_rng()));
+        let found = |seed: u32| -> Vec<CVec> {
+            let rng: XorShiftRng = SeedableRng::from_seed([seed, 1, 2, 3]);
+            WordRangeIter::new(ranges.clone(), &dicts, rng).map(|(_, word)| word).collect()
+        };
+        // The same generator always yields the same order.
+        assert_eq!(found(1), found(1));
+        assert!(found(1) != found(2));
+        let mut sorted = found(1);
+        sorted.sort();
+        assert_eq!(words, sorted);
+    }
 }
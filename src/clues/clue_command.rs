@@ -0,0 +1,95 @@
+//! Clues from an external program.
+//!
+//! The program is started once and kept running while clues are requested. For each word, a line
+//! with the language code and the word, separated by a tab, is written to its standard input, and
+//! it must answer with a single line on its standard output: either the clue or, if it doesn't
+//! know one, an empty line. When its standard input is closed, the program should exit. E. g.:
+//!
+//! ```text
+//! > en	SERVO
+//! < A parallel layout engine
+//! > en	XYZZY
+//! <
+//! ```
+use clues::ClueProvider;
+use std::io::{BufRead, BufReader, Error, ErrorKind, Result, Write};
+use std::process::{Child, ChildStdout, Command, Stdio};
+
+/// A running external program that answers clue requests.
+pub struct ClueCommand {
+    child: Child,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl ClueCommand {
+    /// Starts the given command line: a program name followed by its arguments, separated by
+    /// whitespace.
+    pub fn new(command_line: &str) -> Result<ClueCommand> {
+        let mut words = command_line.split_whitespace();
+        let program = try!(words.next().ok_or(Error::new(ErrorKind::InvalidInput,
+                                                          "the clue command is empty")));
+        let mut child = try!(Command::new(program)
+            .args(&words.collect::<Vec<_>>())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn());
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        Ok(ClueCommand {
+            child: child,
+            stdout: stdout,
+        })
+    }
+}
+
+impl ClueProvider for ClueCommand {
+    fn get_clue(&mut self, word: &str, lang: &str) -> Result<Option<String>> {
+        {
+            let stdin = self.child.stdin.as_mut().unwrap();
+            try!(writeln!(stdin, "{}\t{}", lang, word));
+            try!(stdin.flush());
+        }
+        let mut line = String::new();
+        if try!(self.stdout.read_line(&mut line)) == 0 {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "the clue command has exited"));
+        }
+        let clue = line.trim();
+        Ok(if clue.is_empty() { None } else { Some(clue.to_string()) })
+    }
+}
+
+impl Drop for ClueCommand {
+    fn drop(&mut self) {
+        drop(self.child.stdin.take()); // Closing its input tells the program to exit.
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use clues::ClueProvider;
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::process;
+
+    #[test]
+    fn test_clue_command() {
+        // Answers "<lang>:<word>" for words starting with A, and nothing for the others.
+        let path = env::temp_dir()
+            .join(format!("crosswords-rs-test-clue-command-{}.sh", process::id()));
+        File::create(&path).unwrap().write_all(concat!(
+            "while read lang word; do\n",
+            "  case $word in A*) echo $lang:$word;; *) echo;; esac\n",
+            "done\n").as_bytes()).unwrap();
+        let mut command = ClueCommand::new(&format!("sh {}", path.display())).unwrap();
+        assert_eq!(Some("en:ABC".to_string()), command.get_clue("ABC", "en").unwrap());
+        assert_eq!(None, command.get_clue("XYZ", "en").unwrap());
+        assert_eq!(Some("de:AUTO".to_string()), command.get_clue("AUTO", "de").unwrap());
+        drop(command);
+        fs::remove_file(&path).unwrap();
+        assert!(ClueCommand::new("  ").is_err());
+        let mut command = ClueCommand::new("true").unwrap();
+        assert!(command.get_clue("ABC", "en").is_err()); // It exits without answering.
+    }
+}
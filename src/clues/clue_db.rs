@@ -0,0 +1,183 @@
+//! A local database of clues, read from a file with one clue per line.
+//!
+//! Each line contains the word and the clue, optionally followed by a difficulty - a
+//! non-negative integer - and a language code, separated by tabs or commas. Fields can be quoted
+//! with `"`, and a quote inside a quoted field is written as `""`. Empty lines, lines starting
+//! with `#` and a header line starting with `word` are ignored. The word can also be a phrase like
+//! `ice cream`, which matches the grid word `ICECREAM`. A word can have several clues:
+//!
+//! ```text
+//! word,clue,difficulty,language
+//! servo,"A ""parallel"" layout engine",2,en
+//! servo,Eine Layout-Engine,2,de
+//! rust,Iron oxide,1
+//! ```
+use clues::ClueProvider;
+use cw::CVec;
+use dict::Normalization;
+use std::ascii::AsciiExt;
+use std::collections::HashMap;
+use std::io::{BufRead, Error, ErrorKind, Result};
+use std::u32;
+
+/// A clue from the database, with its optional tags.
+struct Entry {
+    text: String,
+    difficulty: Option<u32>,
+    lang: Option<String>,
+}
+
+/// A collection of clues, each of which can be tagged with a difficulty and a language.
+pub struct ClueDb {
+    entries: HashMap<CVec, Vec<Entry>>,
+    difficulty: Option<u32>,
+    normalization: Normalization,
+}
+
+/// Splits the line into fields at the separator, removing the quotes around quoted fields.
+fn split_fields(line: &str, separator: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => quoted = false,
+                c => field.push(c),
+            }
+        } else if c == '"' && field.trim().is_empty() {
+            field.clear();
+            quoted = true;
+        } else if c == separator {
+            fields.push(field.trim().to_string());
+            field.clear();
+        } else {
+            field.push(c);
+        }
+    }
+    fields.push(field.trim().to_string());
+    fields
+}
+
+impl ClueDb {
+    /// Creates an empty clue database.
+    pub fn new() -> ClueDb {
+        ClueDb {
+            entries: HashMap::new(),
+            difficulty: None,
+            normalization: Normalization::Transliterate,
+        }
+    }
+
+    /// Sets the preferred difficulty and return the modified `ClueDb`. Of several clues for the
+    /// same word, the one with the closest difficulty is selected.
+    pub fn with_difficulty(mut self, difficulty: u32) -> ClueDb {
+        self.difficulty = Some(difficulty);
+        self
+    }
+
+    /// Sets how the words are normalized, which must match the dictionaries, and returns the
+    /// modified `ClueDb`. This must be called before reading any clues.
+    pub fn with_normalization(mut self, normalization: Normalization) -> ClueDb {
+        self.normalization = normalization;
+        self
+    }
+
+    /// Adds the clues from the given reader, where the fields are separated by `separator`.
+    pub fn read<T: BufRead>(&mut self, reader: T, separator: char) -> Result<()> {
+        for (i, line) in reader.lines().enumerate() {
+            let line = try!(line);
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields = split_fields(&line, separator);
+            if i == 0 && fields[0].eq_ignore_ascii_case("word") {
+                continue;
+            }
+            let invalid = |msg| Error::new(ErrorKind::InvalidData,
+                                           format!("line {}: {}", i + 1, msg));
+            if fields.len() < 2 || fields[1].is_empty() {
+                return Err(invalid("missing clue".to_string()));
+            }
+            let difficulty = match fields.get(2).map(|s| &s[..]) {
+                None | Some("") => None,
+                Some(s) => Some(try!(s.parse().map_err(|_| {
+                    invalid(format!("invalid difficulty {}", s))
+                }))),
+            };
+            let word = match self.normalization.normalize_phrase(fields[0].clone()) {
+                Some((word, _)) => word,
+                None => continue, // It couldn't appear in a grid anyway.
+            };
+            self.entries.entry(word).or_insert(Vec::new()).push(Entry {
+                text: fields[1].clone(),
+                difficulty: difficulty,
+                lang: fields.get(3).cloned().into_iter().find(|s| !s.is_empty()),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl ClueProvider for ClueDb {
+    /// Returns a clue in the given language or without a language tag, preferring the former and
+    /// then the one closest to the preferred difficulty.
+    fn get_clue(&mut self, word: &str, lang: &str) -> Result<Option<String>> {
+        let normalized = self.normalization.normalize_phrase(word.to_string());
+        let entries = match normalized.and_then(|(word, _)| self.entries.get(&word)) {
+            Some(entries) => entries,
+            None => return Ok(None),
+        };
+        Ok(entries.iter()
+            .filter(|entry| entry.lang.iter().all(|l| l.eq_ignore_ascii_case(lang)))
+            .min_by_key(|entry| {
+                let distance = match (entry.difficulty, self.difficulty) {
+                    (Some(d0), Some(d1)) => if d0 > d1 { d0 - d1 } else { d1 - d0 },
+                    (None, Some(_)) => u32::MAX,
+                    (_, None) => 0,
+                };
+                (entry.lang.is_none(), distance)
+            })
+            .map(|entry| entry.text.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clues::ClueProvider;
+    use dict::Normalization;
+
+    #[test]
+    fn test_read() {
+        let csv = concat!("word,clue,difficulty,language\n",
+                          "servo,\"A \"\"parallel\"\" layout engine\",2,en\n",
+                          "# A comment\n",
+                          "servo,\"Eine Layout-Engine\",2,de\n",
+                          "\n",
+                          "rust,Iron oxide,1\n",
+                          "Rust,A programming language,3\n");
+        let mut db = ClueDb::new().with_difficulty(3);
+        db.read(csv.as_bytes(), ',').unwrap();
+        db.read("Straße\tA road\nice cream\tA frozen dessert\n".as_bytes(), '\t').unwrap();
+        let mut get_clue = |word, lang| db.get_clue(word, lang).unwrap();
+        assert_eq!(Some("A \"parallel\" layout engine".to_string()), get_clue("SERVO", "en"));
+        assert_eq!(Some("Eine Layout-Engine".to_string()), get_clue("SERVO", "de"));
+        assert_eq!(None, get_clue("SERVO", "fr"));
+        assert_eq!(Some("A programming language".to_string()), get_clue("RUST", "fr"));
+        assert_eq!(Some("A road".to_string()), get_clue("STRASSE", "de"));
+        assert_eq!(Some("A frozen dessert".to_string()), get_clue("ICECREAM", "en"));
+        assert_eq!(Some("A frozen dessert".to_string()), get_clue("Ice cream", "en"));
+        assert_eq!(None, get_clue("FOO", "en"));
+        assert!(db.read("foo,bar,hard\n".as_bytes(), ',').is_err());
+        let mut db = ClueDb::new().with_normalization(Normalization::Keep);
+        db.read("ñandú\tUn ave\n".as_bytes(), '\t').unwrap();
+        assert_eq!(Some("Un ave".to_string()), db.get_clue("ÑANDÚ", "es").unwrap());
+        assert_eq!(None, db.get_clue("NANDU", "es").unwrap());
+    }
+}
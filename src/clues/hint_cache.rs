@@ -0,0 +1,147 @@
+//! A directory with the downloaded articles and the hints extracted from them.
+//!
+//! The entries are stored in one subdirectory per language, in files named after the word, with
+//! the extension `article` for the raw article and `hint` for the extracted hint. An empty hint
+//! file records that there is no hint for the word. Characters other than ASCII letters and digits
+//! are escaped in the file names as six hex digits, e. g. `%00002f` for `/`:
+//!
+//! ```text
+//! cache/en/RUST.article
+//! cache/en/RUST.hint
+//! cache/en/XYZZY.hint
+//! ```
+use std::ascii::AsciiExt;
+use std::fs::{self, File};
+use std::io::{ErrorKind, Read, Result, Write};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+/// A cache of articles and hints, keyed by language and word.
+pub struct HintCache {
+    dir: PathBuf,
+    max_age: Option<Duration>,
+}
+
+/// Returns the word with all characters other than ASCII letters and digits escaped.
+fn escape_word(word: &str) -> String {
+    word.chars().map(|c| if c.is_ascii() && c.is_alphanumeric() {
+        c.to_string()
+    } else {
+        format!("%{:06x}", c as u32)
+    }).collect()
+}
+
+impl HintCache {
+    /// Creates a cache in the given directory, which is created when the first entry is written.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> HintCache {
+        HintCache {
+            dir: dir.into(),
+            max_age: None,
+        }
+    }
+
+    /// Sets the time after which entries are considered stale and ignored, and returns the
+    /// modified `HintCache`. By default, entries never expire.
+    pub fn with_max_age(mut self, max_age: Duration) -> HintCache {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Returns the path of the file containing the given entry.
+    fn path(&self, lang: &str, word: &str, extension: &str) -> PathBuf {
+        self.dir.join(escape_word(lang)).join(format!("{}.{}", escape_word(word), extension))
+    }
+
+    /// Returns whether the file at the given path is older than the maximum age.
+    fn is_expired(&self, path: &PathBuf) -> Result<bool> {
+        let max_age = match self.max_age {
+            None => return Ok(false),
+            Some(max_age) => max_age,
+        };
+        let modified = try!(try!(fs::metadata(path)).modified());
+        // A modification time in the future counts as brand new.
+        let age = SystemTime::now().duration_since(modified).unwrap_or(Duration::from_secs(0));
+        Ok(age >= max_age)
+    }
+
+    /// Returns the content of the given entry, or `None` if it doesn't exist or is expired.
+    fn read(&self, lang: &str, word: &str, extension: &str) -> Result<Option<String>> {
+        let path = self.path(lang, word, extension);
+        let mut file = match File::open(&path) {
+            Ok(file) => file,
+            Err(ref err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        if try!(self.is_expired(&path)) {
+            return Ok(None);
+        }
+        let mut text = String::new();
+        try!(file.read_to_string(&mut text));
+        Ok(Some(text))
+    }
+
+    /// Writes the given entry, replacing an existing one.
+    fn write(&self, lang: &str, word: &str, extension: &str, text: &str) -> Result<()> {
+        let path = self.path(lang, word, extension);
+        try!(fs::create_dir_all(path.parent().unwrap()));
+        let mut file = try!(File::create(path));
+        file.write_all(text.as_bytes())
+    }
+
+    /// Returns the cached article about the word, or `None` if it is not cached.
+    pub fn get_article(&self, lang: &str, word: &str) -> Result<Option<String>> {
+        self.read(lang, word, "article")
+    }
+
+    /// Stores the article about the word.
+    pub fn put_article(&self, lang: &str, word: &str, article: &str) -> Result<()> {
+        self.write(lang, word, "article", article)
+    }
+
+    /// Returns `None` if no hint for the word is cached, `Some(None)` if it is cached that there
+    /// is no hint, and `Some(Some(hint))` otherwise.
+    pub fn get_hint(&self, lang: &str, word: &str) -> Result<Option<Option<String>>> {
+        Ok(try!(self.read(lang, word, "hint"))
+            .map(|hint| if hint.is_empty() { None } else { Some(hint) }))
+    }
+
+    /// Stores the hint for the word, or that there is none.
+    pub fn put_hint(&self, lang: &str, word: &str, hint: Option<&str>) -> Result<()> {
+        self.write(lang, word, "hint", hint.unwrap_or(""))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::process;
+    use std::time::Duration;
+
+    #[test]
+    fn test_hint_cache() {
+        let dir = env::temp_dir()
+            .join(format!("crosswords-rs-test-hint-cache-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let cache = HintCache::new(dir.clone());
+        assert_eq!(None, cache.get_article("en", "RUST").unwrap());
+        assert_eq!(None, cache.get_hint("en", "RUST").unwrap());
+        cache.put_article("en", "RUST", "'''Rust''' is iron oxide.").unwrap();
+        cache.put_hint("en", "RUST", Some("iron oxide")).unwrap();
+        cache.put_hint("en", "XYZZY", None).unwrap();
+        cache.put_hint("de", "A/B", Some("Schrägstrich")).unwrap();
+        assert_eq!(Some("'''Rust''' is iron oxide.".to_string()),
+                   cache.get_article("en", "RUST").unwrap());
+        assert_eq!(Some(Some("iron oxide".to_string())), cache.get_hint("en", "RUST").unwrap());
+        assert_eq!(None, cache.get_hint("de", "RUST").unwrap());
+        assert_eq!(Some(None), cache.get_hint("en", "XYZZY").unwrap());
+        assert_eq!(Some(Some("Schrägstrich".to_string())), cache.get_hint("de", "A/B").unwrap());
+        assert!(dir.join("de").join("A%00002fB.hint").is_file());
+        let expired = HintCache::new(dir.clone()).with_max_age(Duration::from_secs(0));
+        assert_eq!(None, expired.get_hint("en", "RUST").unwrap());
+        let fresh = HintCache::new(dir.clone()).with_max_age(Duration::from_secs(3600));
+        assert_eq!(Some(Some("iron oxide".to_string())), fresh.get_hint("en", "RUST").unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,124 @@
+//! Sources for the clues of the words in a crosswords grid.
+mod clue_command;
+mod clue_db;
+mod hint_cache;
+mod wikipedia;
+
+pub use clues::clue_command::ClueCommand;
+pub use clues::clue_db::ClueDb;
+pub use clues::hint_cache::HintCache;
+pub use clues::wikipedia::Wikipedia;
+
+use std::collections::HashMap;
+use std::io::Result;
+
+/// A source of clues for words.
+pub trait ClueProvider {
+    /// Returns a clue for the given word in the given language, or `None` if there is none.
+    fn get_clue(&mut self, word: &str, lang: &str) -> Result<Option<String>>;
+}
+
+/// A list of clue providers that are asked in turn, until one of them has a clue for the word.
+pub struct ClueChain {
+    providers: Vec<Box<ClueProvider>>,
+}
+
+impl ClueChain {
+    /// Creates a chain without any providers.
+    pub fn new() -> ClueChain {
+        ClueChain { providers: Vec::new() }
+    }
+
+    /// Appends a provider that is only asked for the words the previous ones have no clue for,
+    /// and returns the modified `ClueChain`.
+    pub fn with_provider(mut self, provider: Box<ClueProvider>) -> ClueChain {
+        self.providers.push(provider);
+        self
+    }
+
+    /// Returns whether the chain contains any providers.
+    pub fn is_empty(&self) -> bool {
+        self.providers.is_empty()
+    }
+}
+
+impl ClueProvider for ClueChain {
+    /// Returns the first clue found by any of the providers. If none of them has a clue, the first
+    /// error, if any, is returned.
+    fn get_clue(&mut self, word: &str, lang: &str) -> Result<Option<String>> {
+        let mut first_err = None;
+        for provider in &mut self.providers {
+            match provider.get_clue(word, lang) {
+                Ok(Some(clue)) => return Ok(Some(clue)),
+                Ok(None) => (),
+                Err(err) => {
+                    first_err = first_err.or(Some(err));
+                }
+            }
+        }
+        first_err.map_or(Ok(None), Err)
+    }
+}
+
+/// Returns a map from the given words to their clues in the given language. For each word, a
+/// list of spellings is given, e. g. `Übermut` for `UEBERMUT`. The provider is asked for each of
+/// them in turn, until it has a clue for one. Words without a clue are left out. If the provider
+/// fails for a spelling, the error is printed and the next one is tried.
+pub fn get_clues_for_spellings<P: ClueProvider + ?Sized,
+                               T: Iterator<Item = (String, Vec<String>)>>(provider: &mut P,
+                                                                          words: T, lang: &str)
+        -> HashMap<String, String> {
+    let mut clues = HashMap::new();
+    for (word, spellings) in words {
+        for spelling in spellings {
+            match provider.get_clue(&spelling, lang) {
+                Ok(Some(clue)) => {
+                    clues.insert(word, clue);
+                    break;
+                }
+                Ok(None) => (),
+                Err(err) => println!("Failed to get a clue for {}: {}", spelling, err),
+            }
+        }
+    }
+    clues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Error, ErrorKind, Result};
+
+    /// A provider that knows a single clue and fails for every other word.
+    struct Fixed(&'static str, &'static str);
+
+    impl ClueProvider for Fixed {
+        fn get_clue(&mut self, word: &str, _lang: &str) -> Result<Option<String>> {
+            match word {
+                w if w == self.0 => Ok(Some(self.1.to_string())),
+                "NONE" => Ok(None),
+                _ => Err(Error::new(ErrorKind::NotFound, self.0)),
+            }
+        }
+    }
+
+    #[test]
+    fn test_chain() {
+        let mut chain = ClueChain::new()
+            .with_provider(Box::new(Fixed("FOO", "first")))
+            .with_provider(Box::new(Fixed("BAR", "second")))
+            .with_provider(Box::new(Fixed("FOO", "third")));
+        assert_eq!(Some("first".to_string()), chain.get_clue("FOO", "en").unwrap());
+        assert_eq!(Some("second".to_string()), chain.get_clue("BAR", "en").unwrap());
+        assert_eq!(None, chain.get_clue("NONE", "en").unwrap());
+        assert_eq!("FOO", chain.get_clue("BAZ", "en").unwrap_err().to_string());
+        let words = vec!("FOO", "BAZ", "NONE").into_iter()
+            .map(|s| (s.to_string(), vec!(s.to_string())));
+        let clues = get_clues_for_spellings(&mut chain, words, "en");
+        assert_eq!(1, clues.len());
+        assert_eq!("first", clues["FOO"]);
+        let words = vec!(("QUX".to_string(), vec!("NONE".to_string(), "BAR".to_string())));
+        let clues = get_clues_for_spellings(&mut chain, words.into_iter(), "en");
+        assert_eq!("second", clues["QUX"]);
+    }
+}
@@ -0,0 +1,147 @@
+//! Clues taken from the beginning of the Wikipedia article about the word.
+use clues::{ClueProvider, HintCache};
+use get_hints::{download_article, extract_hint, wikipedia_server, HintError};
+use hint_rules::HintRules;
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind, Result};
+use std::result;
+
+/// Downloads the article about each word from the Wikipedia in the requested language.
+pub struct Wikipedia {
+    server: Option<String>,
+    cache: Option<HintCache>,
+    offline: bool,
+    rules: HashMap<String, HintRules>,
+}
+
+/// Converts the result of a hint lookup, treating a missing article or hint as no clue.
+fn to_clue(result: result::Result<String, HintError>) -> Result<Option<String>> {
+    match result {
+        Ok(hint) => Ok(Some(hint)),
+        Err(HintError::NotFound) | Err(HintError::NoHint) => Ok(None),
+        Err(HintError::Io(err)) => Err(err),
+        Err(err) => Err(Error::new(ErrorKind::Other, err)),
+    }
+}
+
+impl Wikipedia {
+    /// Creates a provider that asks `http://<lang>.wikipedia.org`.
+    pub fn new() -> Wikipedia {
+        Wikipedia {
+            server: None,
+            cache: None,
+            offline: false,
+            rules: HashMap::new(),
+        }
+    }
+
+    /// Sets the address of the server to ask instead, e. g. `http://localhost:8080`, and returns
+    /// the modified `Wikipedia`.
+    pub fn with_server(mut self, server: String) -> Wikipedia {
+        self.server = Some(server);
+        self
+    }
+
+    /// Sets the cache in which the articles and hints are stored and looked up first, and returns
+    /// the modified `Wikipedia`.
+    pub fn with_cache(mut self, cache: HintCache) -> Wikipedia {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// If `offline` is `true`, only cached entries are used and the server is never asked.
+    /// Returns the modified `Wikipedia`.
+    pub fn with_offline(mut self, offline: bool) -> Wikipedia {
+        self.offline = offline;
+        self
+    }
+
+    /// Sets the rules for extracting hints from articles in the given language, instead of the
+    /// built-in ones, and returns the modified `Wikipedia`.
+    pub fn with_rules(mut self, lang: &str, rules: HintRules) -> Wikipedia {
+        self.rules.insert(lang.to_string(), rules);
+        self
+    }
+
+    /// Returns the article about the word from the cache or, if it is not cached, from the
+    /// server. Returns `None` if it is neither cached nor allowed to be downloaded.
+    fn get_article(&self, word: &str, lang: &str, rules: &HintRules) -> Result<Option<String>> {
+        if let Some(ref cache) = self.cache {
+            if let Some(article) = try!(cache.get_article(lang, word)) {
+                return Ok(Some(article));
+            }
+        }
+        if self.offline {
+            return Ok(None);
+        }
+        let server = self.server.clone().unwrap_or_else(|| wikipedia_server(lang));
+        let article = match download_article(&server, word, rules) {
+            Ok(article) => article,
+            Err(HintError::NotFound) => {
+                if let Some(ref cache) = self.cache {
+                    try!(cache.put_hint(lang, word, None));
+                }
+                return Ok(None);
+            }
+            Err(err) => return to_clue(Err(err)),
+        };
+        if let Some(ref cache) = self.cache {
+            try!(cache.put_article(lang, word, &article));
+        }
+        Ok(Some(article))
+    }
+}
+
+impl ClueProvider for Wikipedia {
+    /// Returns `None` if there is no article about the word or it contains no usable text.
+    fn get_clue(&mut self, word: &str, lang: &str) -> Result<Option<String>> {
+        if let Some(ref cache) = self.cache {
+            if let Some(hint) = try!(cache.get_hint(lang, word)) {
+                return Ok(hint);
+            }
+        }
+        let rules = self.rules.entry(lang.to_string())
+            .or_insert_with(|| HintRules::for_lang(lang)).clone();
+        let article = match try!(self.get_article(word, lang, &rules)) {
+            Some(article) => article,
+            None => return Ok(None),
+        };
+        let hint = try!(to_clue(extract_hint(article, word, &rules)));
+        if let Some(ref cache) = self.cache {
+            try!(cache.put_hint(lang, word, hint.as_ref().map(|s| &s[..])));
+        }
+        Ok(hint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clues::{ClueProvider, HintCache};
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    #[test]
+    fn test_offline() {
+        let dir = env::temp_dir()
+            .join(format!("crosswords-rs-test-wikipedia-offline-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let cache = HintCache::new(dir.clone());
+        cache.put_article("en", "RUST", "'''Rust''' is a programming language.\n").unwrap();
+        cache.put_hint("en", "SERVO", Some("a layout engine")).unwrap();
+        // The server is unreachable, so it must not be asked.
+        let mut wikipedia = Wikipedia::new()
+            .with_server("http://127.0.0.1:1".to_string())
+            .with_cache(HintCache::new(dir.clone()))
+            .with_offline(true);
+        assert_eq!(Some("a programming language".to_string()),
+                   wikipedia.get_clue("RUST", "en").unwrap());
+        assert_eq!(Some(Some("a programming language".to_string())),
+                   cache.get_hint("en", "RUST").unwrap());
+        assert_eq!(Some("a layout engine".to_string()),
+                   wikipedia.get_clue("SERVO", "en").unwrap());
+        assert_eq!(None, wikipedia.get_clue("MISSING", "en").unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
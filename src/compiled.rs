@@ -0,0 +1,183 @@
+//! Compiled dictionaries, which load much faster than the word lists they are created from.
+//!
+//! A compiled file contains the words and scores of each dictionary, the lists of words satisfying
+//! each `WordConstraint` and the original spellings of the words, so that nothing needs to be
+//! indexed when it is loaded. The word statistics are computed from the constraint lists.
+//!
+//! The file starts with a magic number and the format version, followed by a fingerprint of the
+//! contents of the source word lists and the settings they were read with, so that an outdated
+//! file is detected. All numbers are stored as little-endian 32-bit integers, except for the
+//! 64-bit fingerprint. Characters are stored as numbers, and strings as their UTF-8 bytes, both
+//! preceded by their length.
+use cw::CVec;
+use dict::{Dict, Normalization, Spellings};
+use std::char;
+use std::fs::File;
+use std::io::{Error, ErrorKind, Read, Result, Write};
+
+const MAGIC: &'static [u8] = b"CWDICT";
+const VERSION: u32 = 1;
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+pub fn invalid_data(msg: String) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+pub fn write_u32<W: Write>(writer: &mut W, value: u32) -> Result<()> {
+    writer.write_all(&[value as u8, (value >> 8) as u8, (value >> 16) as u8, (value >> 24) as u8])
+}
+
+pub fn read_u32<R: Read>(reader: &mut R) -> Result<u32> {
+    let mut bytes = [0; 4];
+    try!(reader.read_exact(&mut bytes));
+    Ok(bytes.iter().rev().fold(0, |value, &byte| (value << 8) | byte as u32))
+}
+
+fn write_u64<W: Write>(writer: &mut W, value: u64) -> Result<()> {
+    try!(write_u32(writer, value as u32));
+    write_u32(writer, (value >> 32) as u32)
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64> {
+    let low = try!(read_u32(reader)) as u64;
+    Ok(low | (try!(read_u32(reader)) as u64) << 32)
+}
+
+pub fn write_chars<W: Write>(writer: &mut W, chars: &[char]) -> Result<()> {
+    try!(write_u32(writer, chars.len() as u32));
+    for &c in chars {
+        try!(write_u32(writer, c as u32));
+    }
+    Ok(())
+}
+
+/// Reads a vector of characters. The vector grows as the characters are read, so a corrupt length
+/// can't make it allocate more memory than the input contains.
+pub fn read_chars<R: Read>(reader: &mut R) -> Result<CVec> {
+    let len = try!(read_u32(reader));
+    (0..len).map(|_| {
+        let code = try!(read_u32(reader));
+        char::from_u32(code).ok_or_else(|| invalid_data(format!("invalid character {}", code)))
+    }).collect()
+}
+
+pub fn write_string<W: Write>(writer: &mut W, string: &str) -> Result<()> {
+    try!(write_u32(writer, string.len() as u32));
+    writer.write_all(string.as_bytes())
+}
+
+/// Reads a string, allocating no more memory than the input contains even if the length is corrupt.
+pub fn read_string<R: Read>(reader: &mut R) -> Result<String> {
+    let len = try!(read_u32(reader)) as u64;
+    let mut bytes = Vec::new();
+    if try!(reader.by_ref().take(len).read_to_end(&mut bytes)) as u64 != len {
+        return Err(Error::new(ErrorKind::UnexpectedEof, "the string is truncated"));
+    }
+    String::from_utf8(bytes).map_err(|err| invalid_data(err.to_string()))
+}
+
+/// Returns the FNV-1a hash of the bytes, continuing from the given hash.
+fn fnv_hash(mut hash: u64, bytes: &[u8]) -> u64 {
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+/// Returns a fingerprint of the contents of the given word lists, the way they are normalized and
+/// whether they may contain phrases, and the minimum word length and score.
+pub fn fingerprint(sources: &[(String, Normalization, bool)], min_word_len: usize,
+                   min_score: u32) -> Result<u64> {
+    let mut hash = FNV_OFFSET;
+    for &(ref filename, normalization, phrases) in sources {
+        let mut contents = Vec::new();
+        try!(try!(File::open(filename)).read_to_end(&mut contents));
+        let settings = format!("{:?} {} {}\n", normalization, phrases, contents.len());
+        hash = fnv_hash(fnv_hash(hash, settings.as_bytes()), &contents);
+    }
+    Ok(fnv_hash(hash, format!("{} {}", min_word_len, min_score).as_bytes()))
+}
+
+/// Writes the dictionaries and the spellings of their words, tagged with the given fingerprint.
+pub fn write_compiled<W: Write>(writer: &mut W, fingerprint: u64, dicts: &[Dict],
+                                spellings: &Spellings) -> Result<()> {
+    try!(writer.write_all(MAGIC));
+    try!(write_u32(writer, VERSION));
+    try!(write_u64(writer, fingerprint));
+    try!(write_u32(writer, dicts.len() as u32));
+    for dict in dicts {
+        try!(dict.write_to(writer));
+    }
+    spellings.write_to(writer)
+}
+
+/// Reads the dictionaries and the spellings of their words. Returns `None` if the file was
+/// compiled from different word lists or settings, or in a different format version.
+pub fn read_compiled<R: Read>(reader: &mut R, fingerprint: u64)
+        -> Result<Option<(Vec<Dict>, Spellings)>> {
+    let mut magic = [0; 6];
+    try!(reader.read_exact(&mut magic));
+    if &magic[..] != MAGIC {
+        return Err(invalid_data("not a compiled dictionary".to_string()));
+    }
+    if try!(read_u32(reader)) != VERSION || try!(read_u64(reader)) != fingerprint {
+        return Ok(None);
+    }
+    let count = try!(read_u32(reader));
+    let dicts = try!((0..count).map(|_| Dict::read_from(reader)).collect::<Result<Vec<_>>>());
+    Ok(Some((dicts, try!(Spellings::read_from(reader)))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cw::CVec;
+    use dict::{Dict, Normalization, Spellings};
+    use rand::thread_rng;
+    use std::io::ErrorKind;
+    use std::u32;
+
+    #[test]
+    fn test_round_trip() {
+        let mut spellings = Spellings::new();
+        let tr = Normalization::Transliterate;
+        let words: Vec<CVec> = vec!("Übermut", "ice cream", "foo").into_iter()
+            .filter_map(|word| spellings.add(word.to_string(), tr, true)).collect();
+        let dicts = vec!(Dict::with_scores(words.iter().zip(vec!(10, 60, 30)), &mut thread_rng()),
+                         Dict::new(Vec::<CVec>::new().iter(), &mut thread_rng()));
+        let mut bytes = Vec::new();
+        write_compiled(&mut bytes, 42, &dicts, &spellings).unwrap();
+        assert!(read_compiled(&mut &bytes[..], 43).unwrap().is_none());
+        assert!(read_compiled(&mut &b"foobar"[..], 42).is_err());
+        let (read_dicts, read_spellings) = read_compiled(&mut &bytes[..], 42).unwrap().unwrap();
+        assert_eq!(2, read_dicts.len());
+        assert!(dicts[0].all_words().eq(read_dicts[0].all_words()));
+        assert_eq!(Some(60), read_dicts[0].score(&words[1]));
+        assert_eq!(2, read_dicts[0].matching_words("########".chars().collect()).count());
+        assert_eq!(0, read_dicts[1].all_words().count());
+        assert_eq!("Übermut", read_spellings.display(&words[0]));
+        assert_eq!(Some("(3,5)"), read_spellings.enumeration(&words[1]));
+        assert!(read_compiled(&mut &bytes[..(bytes.len() - 1)], 42).is_err());
+        let mut reversed = Spellings::new();
+        for word in vec!("foo", "ice cream", "Übermut") {
+            reversed.add(word.to_string(), tr, true);
+        }
+        let (mut bytes0, mut bytes1) = (Vec::new(), Vec::new());
+        spellings.write_to(&mut bytes0).unwrap();
+        reversed.write_to(&mut bytes1).unwrap();
+        assert_eq!(bytes0, bytes1);
+    }
+
+    #[test]
+    fn test_corrupt_length() {
+        let mut bytes = Vec::new();
+        write_u32(&mut bytes, u32::MAX).unwrap();
+        bytes.extend_from_slice(b"foo");
+        let err = read_string(&mut &bytes[..]).unwrap_err();
+        assert_eq!(ErrorKind::UnexpectedEof, err.kind());
+        assert!(read_chars(&mut &bytes[..]).is_err());
+    }
+}
@@ -25,6 +25,9 @@ pub type CVec = Vec<char>;
 
 pub const BLOCK: char = '#';
 
+/// A cell that must stay empty: No word may be placed on it.
+pub const SOLID: char = '*';
+
 /// The possible directions for words: `Right` and `Down`.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum Dir {
@@ -74,6 +77,50 @@ impl Crosswords {
         }
     }
 
+    /// Creates a grid with the given cell contents, from left to right and from top to bottom,
+    /// where `BLOCK` denotes an empty cell. The values in `right_border` and `down_border` specify
+    /// whether there is a border to the right of resp. below each cell, excluding the last column
+    /// resp. row. Borders next to empty cells are always added. Returns an error if a letter
+    /// doesn't belong to any word or if a word appears twice.
+    pub fn from_cells(width: usize, height: usize, chars: CVec, right_border: Vec<bool>,
+                      down_border: Vec<bool>) -> Result<Crosswords, String> {
+        if chars.len() != width * height || right_border.len() != (width - 1) * height
+                || down_border.len() != width * (height - 1) {
+            return Err("the grid contents don't match the dimensions".to_string());
+        }
+        let mut cw = Crosswords {
+            width: width,
+            height: height,
+            chars: chars,
+            right_border: right_border,
+            down_border: down_border,
+            words: HashSet::new(),
+        };
+        let points: Vec<Point> = (0..(height as i32))
+            .flat_map(|y| (0..(width as i32)).map(move |x| Point::new(x, y))).collect();
+        for &p in &points {
+            if !cw.is_letter(p) {
+                for &dir in &[Dir::Right, Dir::Down] {
+                    cw.set_border(p, dir, true);
+                    cw.set_border(p - dir.point(), dir, true);
+                }
+            }
+        }
+        for &p in points.iter().filter(|&&p| cw.is_letter(p)) {
+            if cw.both_borders(p, Dir::Right) && cw.both_borders(p, Dir::Down) {
+                return Err(format!("the letter at ({}, {}) doesn't belong to a word", p.x, p.y));
+            }
+        }
+        let words: Vec<CVec> = cw.word_ranges().map(|range| cw.chars(range).collect()).collect();
+        for word in words {
+            if !cw.words.insert(word.clone()) {
+                let word: String = word.into_iter().collect();
+                return Err(format!("the word {} appears twice", word));
+            }
+        }
+        Ok(cw)
+    }
+
     #[inline]
     pub fn get_width(&self) -> usize {
         self.width
@@ -237,12 +284,13 @@ impl Crosswords {
         point.x >= 0 && point.y >= 0 && point.x < self.width as i32 && point.y < self.height as i32
     }
 
-    /// Returns `false` if any cell of the range belongs to a word in the range's direction.
+    /// Returns `false` if any cell of the range belongs to a word in the range's direction or must
+    /// stay empty.
     pub fn is_range_free(&self, range: Range) -> bool {
         let dp = range.dir.point();
         self.contains(range.point) && self.contains(range.point + dp * (range.len - 1))
             && self.get_border(range.point - dp, range.dir)
-            && range.points().all(|p| self.get_border(p, range.dir))
+            && range.points().all(|p| self.get_border(p, range.dir) && !self.is_solid(p))
     }
 
     /// Returns the largest free range containing the point.
@@ -301,11 +349,27 @@ impl Crosswords {
     #[inline]
     pub fn is_letter(&self, point: Point) -> bool {
         match self.get_char(point) {
-            None | Some(BLOCK) => false,
+            None | Some(BLOCK) | Some(SOLID) => false,
             Some(_) => true,
         }
     }
 
+    /// Returns `true` if the point is the coordinate of a cell which must stay empty.
+    #[inline]
+    pub fn is_solid(&self, point: Point) -> bool {
+        self.get_char(point) == Some(SOLID)
+    }
+
+    /// Marks the given empty cell as one that must stay empty, or reverts that. Returns `false` if
+    /// the cell contains a letter or is outside the grid.
+    pub fn set_solid(&mut self, point: Point, solid: bool) -> bool {
+        if !self.contains(point) || self.is_letter(point) {
+            return false;
+        }
+        self.put_char(point, if solid { SOLID } else { BLOCK });
+        true
+    }
+
     fn is_boundary_point(&self, point: Point) -> bool {
         self.get_char(point) == Some(BLOCK) && (self.is_letter(point + Point::new(1, 0))
             || self.is_letter(point + Point::new(-1, 0))
@@ -364,16 +428,68 @@ impl Crosswords {
         self.has_hint_at_dir(point, Dir::Right) || self.has_hint_at_dir(point, Dir::Down)
     }
 
+    /// Returns the ranges of all words together with their hint numbers, ordered by number and
+    /// with the horizontal before the vertical word. The numbers are the same as in the
+    /// `PrintItem::CharHint` items.
+    pub fn numbered_word_ranges(&self) -> Vec<(u32, Range)> {
+        let mut result = Vec::new();
+        let mut hint_count = 0;
+        for y in 0..(self.height as i32) {
+            for x in 0..(self.width as i32) {
+                let p = Point::new(x, y);
+                if self.has_hint_at(p) {
+                    hint_count += 1;
+                }
+                for &dir in &[Dir::Right, Dir::Down] {
+                    if self.has_hint_at_dir(p, dir) {
+                        result.push((hint_count, self.get_word_range_at(p, dir)));
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// Returns the cell that holds the clue for the word in the given range in an arrowword grid:
+    /// the cell before the word if it is inside the grid and doesn't contain a letter, otherwise
+    /// the cell before the word's first letter in the other direction. Returns `None` if neither
+    /// of them is available.
+    pub fn get_clue_cell(&self, range: Range) -> Option<Point> {
+        vec!(range.point - range.dir.point(), range.point - range.dir.other().point()).into_iter()
+            .find(|&p| self.contains(p) && !self.is_letter(p))
+    }
+
+    /// Returns the ranges of the words whose clues are in the given cell.
+    pub fn get_clued_ranges(&self, point: Point) -> Vec<Range> {
+        let mut result = Vec::new();
+        for &start in &[point + Point::new(1, 0), point + Point::new(0, 1)] {
+            for &dir in &[Dir::Right, Dir::Down] {
+                if self.has_hint_at_dir(start, dir) {
+                    let range = self.get_word_range_at(start, dir);
+                    if self.get_clue_cell(range) == Some(point) {
+                        result.push(range);
+                    }
+                }
+            }
+        }
+        result
+    }
+
     /// Returns `true` if the grid is empty, i. e. it contains no words and every cell is a block.
     pub fn is_empty(&self) -> bool {
         self.words.is_empty()
     }
 
-    /// Returns `true` if the grid is full, i. e. every cell contains a letter.
+    /// Returns `true` if the grid is full, i. e. every cell contains a letter or must stay empty.
     pub fn is_full(&self) -> bool {
         (0..(self.width * self.height)).all(|p| self.chars[p] != BLOCK)
     }
 
+    /// Returns the number of cells that contain a letter.
+    pub fn count_letters(&self) -> usize {
+        self.chars.iter().filter(|&&c| c != BLOCK && c != SOLID).count()
+    }
+
     /// Returns the number of borders inside the grid.
     pub fn count_borders(&self) -> usize {
         self.right_border.iter().chain(self.down_border.iter()).filter(|&&b| b).count()
@@ -416,7 +532,7 @@ impl Display for Crosswords {
                 PrintItem::HorizBorder(true) => '\u{2014}',
                 PrintItem::Cross(false) | PrintItem::VertBorder(false)
                     | PrintItem::HorizBorder(false) => ' ',
-                PrintItem::Block => '\u{2588}',
+                PrintItem::Block(_) => '\u{2588}',
                 PrintItem::CharHint(c, _) => c,
                 PrintItem::LineBreak => '\n',
             }.to_string()[..]))
@@ -448,4 +564,40 @@ mod tests {
         assert_eq!(true, cw.try_word(p01, Dir::Right, &"BAR".chars().collect()));
         assert_eq!(true, cw.try_word(p00, Dir::Down, &"BB".chars().collect()));
     }
+
+    #[test]
+    fn test_clue_cells() {
+        let mut cw = Crosswords::new(3, 3);
+        cw.try_word(Point::new(1, 0), Dir::Right, &"AB".chars().collect());
+        cw.try_word(Point::new(1, 0), Dir::Down, &"AC".chars().collect());
+        cw.try_word(Point::new(0, 2), Dir::Right, &"DEF".chars().collect());
+        let p00 = Point::new(0, 0);
+        assert_eq!(Some(p00), cw.get_clue_cell(cw.get_word_range_at(Point::new(1, 0), Dir::Right)));
+        // The cell above AC is outside the grid, so its clue is to the left of the first letter.
+        assert_eq!(Some(p00), cw.get_clue_cell(cw.get_word_range_at(Point::new(1, 0), Dir::Down)));
+        assert_eq!(2, cw.get_clued_ranges(p00).len());
+        assert_eq!(Some(Point::new(0, 1)),
+                   cw.get_clue_cell(cw.get_word_range_at(Point::new(0, 2), Dir::Right)));
+        // The cell before BXF is outside the grid, and the one above is the A.
+        cw.try_word(Point::new(2, 0), Dir::Down, &"BXF".chars().collect());
+        assert_eq!(None, cw.get_clue_cell(cw.get_word_range_at(Point::new(2, 0), Dir::Down)));
+    }
+
+    #[test]
+    fn test_from_cells() {
+        let mut cw = Crosswords::new(3, 2);
+        cw.try_word(Point::new(0, 0), Dir::Right, &"AB".chars().collect());
+        cw.try_word(Point::new(0, 0), Dir::Down, &"AC".chars().collect());
+        cw.try_word(Point::new(1, 1), Dir::Right, &"DE".chars().collect());
+        let cw2 = Crosswords::from_cells(3, 2, cw.chars.clone(), cw.right_border.clone(),
+                                         cw.down_border.clone()).unwrap();
+        assert_eq!(cw.to_string(), cw2.to_string());
+        assert_eq!(cw.words, cw2.words);
+        // A letter without a word:
+        let chars = "A#BC".chars().collect();
+        assert!(Crosswords::from_cells(2, 2, chars, vec!(false; 2), vec!(true; 2)).is_err());
+        // A duplicate word:
+        let chars = "ABAB".chars().collect();
+        assert!(Crosswords::from_cells(2, 2, chars, vec!(false; 2), vec!(true; 2)).is_err());
+    }
 }
@@ -1,4 +1,4 @@
-use cw::{BLOCK, Crosswords, Dir, Point};
+use cw::{BLOCK, Crosswords, Dir, Point, SOLID};
 
 /// An element representing a part of a crosswords grid: an element of the cell's borders, a cell
 /// and its contents or a line break. It should be converted to a textual or graphical
@@ -15,8 +15,9 @@ pub enum PrintItem {
     /// A crossing point of borders. It is considered thick (value `true`) if at least two of the
     /// four lines crossing here are thick.
     Cross(bool),
-    /// A solid block that is left empty in the crossword's solution. It does not belong to a word.
-    Block,
+    /// A solid block at the given point that is left empty in the crossword's solution. It does
+    /// not belong to a word.
+    Block(Point),
     /// A cell that belongs to one or two words and contains the given character. If one or two
     /// words begin in this cell, the second value will be `n`, where this is the `n`-th cell
     /// containing the beginning of a word.
@@ -79,7 +80,7 @@ impl<'a> Iterator for PrintIter<'a> {
                 result = PrintItem::HorizBorder(self.cw.get_border(self.point, Dir::Down));
             } else {
                 result = match self.cw.get_char(self.point).unwrap() {
-                    BLOCK => PrintItem::Block,
+                    BLOCK | SOLID => PrintItem::Block(self.point),
                     c => PrintItem::CharHint(c, if self.cw.has_hint_at(self.point) {
                             self.hint_count += 1;
                             Some(self.hint_count)
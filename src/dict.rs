@@ -1,28 +1,56 @@
+use compiled::{invalid_data, read_chars, read_string, read_u32, write_chars, write_string,
+               write_u32};
 use cw::{CVec, BLOCK};
-use rand;
 use rand::Rng;
 use std::ascii::AsciiExt;
 use std::cmp;
-use std::collections::{HashMap, HashSet};
+use std::collections::{hash_map, HashMap, HashSet};
+use std::io::{Read, Result, Write};
 use std::slice;
 use word_constraint::WordConstraint;
 
-fn matches(word: &CVec, pattern: &CVec) -> bool {
-    word.len() <= pattern.len()
-        && word.iter().zip(pattern.iter()).all(|(&cw, &cp)| cw == cp || cp == BLOCK)
+/// Returns the position of the first element in the sorted list, at or after `start`, that is not
+/// less than `target`. The distance from `start` is doubled in each step, so that this is fast if
+/// the result is close to `start`, and at most logarithmic in the length of the list otherwise.
+fn gallop(list: &[usize], start: usize, target: usize) -> usize {
+    let mut low = start;
+    let mut step = 1;
+    while low + step < list.len() && list[low + step] < target {
+        low += step;
+        step *= 2;
+    }
+    let high = cmp::min(low + step, list.len());
+    match list[low..high].binary_search(&target) {
+        Ok(i) | Err(i) => low + i,
+    }
 }
 
-/// An iterator over all words satisfying a given `WordConstraint`.
+/// An iterator over all words matching a given pattern. It goes through the shortest of the lists
+/// of words satisfying the pattern's `WordConstraint`s, and returns the words that are contained in
+/// all the other lists, too.
 pub struct PatternIter<'a> {
     dict: &'a Dict,
-    pattern: CVec,
-    list: &'a Vec<usize>,
+    list: &'a [usize],
     index: usize,
+    /// The other lists, each with the position up to which all its entries are smaller than the
+    /// current candidate.
+    others: Vec<(&'a [usize], usize)>,
 }
 
 impl<'a> PatternIter<'a> {
-    fn get_word(&self) -> Option<&'a CVec> {
-        self.list.get(self.index).and_then(|&i| self.dict.words.get(i))
+    /// Returns the next matching word together with its score.
+    pub fn next_scored(&mut self) -> Option<(&'a CVec, u32)> {
+        'candidates: while let Some(&i) = self.list.get(self.index) {
+            self.index += 1;
+            for other in &mut self.others {
+                other.1 = gallop(other.0, other.1, i);
+                if other.0.get(other.1) != Some(&i) {
+                    continue 'candidates;
+                }
+            }
+            return Some((&self.dict.words[i], self.dict.scores[i]));
+        }
+        None
     }
 }
 
@@ -30,20 +58,243 @@ impl<'a> Iterator for PatternIter<'a> {
     type Item = &'a CVec;
 
     fn next(&mut self) -> Option<&'a CVec> {
-        loop {
-            let word = self.get_word();
-            self.index += 1;
-            if word.iter().all(|w| matches(w, &self.pattern)) {
-                return word;
+        self.next_scored().map(|(word, _)| word)
+    }
+}
+
+/// The score of words in lists without scores. Scored lists usually use scores from 0 to 100.
+pub const DEFAULT_SCORE: u32 = 50;
+
+/// Splits a line of the form `word;score` into the word and the score. If there is no valid score,
+/// the whole line is returned as the word.
+pub fn split_score(line: &str) -> (&str, Option<u32>) {
+    if let Some(pos) = line.rfind(';') {
+        if let Ok(score) = line[(pos + 1)..].trim().parse() {
+            return (&line[..pos], Some(score));
+        }
+    }
+    (line, None)
+}
+
+/// Returns the Latin letter without diacritics, or the letters it is usually spelled as, or `None`
+/// if the letter is not in the table.
+fn fold_letter(c: char) -> Option<&'static str> {
+    Some(match c {
+        'À' | 'Á' | 'Â' | 'Ã' | 'Ä' | 'Å' | 'Ā' | 'Ă' | 'Ą' => "A",
+        'Æ' => "AE",
+        'Ç' | 'Ć' | 'Ĉ' | 'Ċ' | 'Č' => "C",
+        'Ď' | 'Đ' | 'Ð' => "D",
+        'È' | 'É' | 'Ê' | 'Ë' | 'Ē' | 'Ĕ' | 'Ė' | 'Ę' | 'Ě' => "E",
+        'Ĝ' | 'Ğ' | 'Ġ' | 'Ģ' => "G",
+        'Ĥ' | 'Ħ' => "H",
+        'Ì' | 'Í' | 'Î' | 'Ï' | 'Ĩ' | 'Ī' | 'Ĭ' | 'Į' | 'İ' => "I",
+        'Ĵ' => "J",
+        'Ķ' => "K",
+        'Ĺ' | 'Ļ' | 'Ľ' | 'Ŀ' | 'Ł' => "L",
+        'Ñ' | 'Ń' | 'Ņ' | 'Ň' => "N",
+        'Ò' | 'Ó' | 'Ô' | 'Õ' | 'Ö' | 'Ø' | 'Ō' | 'Ŏ' | 'Ő' => "O",
+        'Œ' => "OE",
+        'Ŕ' | 'Ŗ' | 'Ř' => "R",
+        'Ś' | 'Ŝ' | 'Ş' | 'Š' | 'Ș' => "S",
+        'Ţ' | 'Ť' | 'Ŧ' | 'Ț' => "T",
+        'Þ' => "TH",
+        'Ù' | 'Ú' | 'Û' | 'Ü' | 'Ũ' | 'Ū' | 'Ŭ' | 'Ů' | 'Ű' | 'Ų' => "U",
+        'Ŵ' => "W",
+        'Ý' | 'Ÿ' | 'Ŷ' => "Y",
+        'Ź' | 'Ż' | 'Ž' => "Z",
+        _ => return None,
+    })
+}
+
+/// How the words from a dictionary are converted to the letters in the grid. In all cases, the
+/// letters are converted to upper case, and words containing anything but letters are rejected.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Normalization {
+    /// Keep all letters as they are, including diacritics and non-Latin alphabets, e. g. `ÑANDÚ`.
+    Keep,
+    /// Replace German umlauts with diphthongs and `ß` with `SS`, and reject words with any other
+    /// non-ASCII letters, e. g. `UEBERMUT`.
+    Transliterate,
+    /// Remove the diacritics from Latin letters, e. g. `NANDU` or `UBERMUT`, but keep letters from
+    /// other alphabets.
+    Fold,
+}
+
+impl Normalization {
+    /// Returns the normalization with the given name: `keep`, `transliterate` or `fold`.
+    pub fn from_name(name: &str) -> Option<Normalization> {
+        match name {
+            "keep" => Some(Normalization::Keep),
+            "transliterate" => Some(Normalization::Transliterate),
+            "fold" => Some(Normalization::Fold),
+            _ => None,
+        }
+    }
+
+    /// Return the word as a char vector in upper case, normalized according to this setting, or
+    /// `None` if it contains anything but letters or is shorter than two letters.
+    pub fn normalize(self, string_word: String) -> Option<CVec> {
+        match self.convert(string_word.trim()) {
+            Some(word) => if word.len() > 1 { Some(word) } else { None },
+            None => None,
+        }
+    }
+
+    /// Like `normalize`, but also accepts phrases of several words separated by spaces or hyphens,
+    /// e. g. `ICECREAM` for `ice cream`. If there is more than one word, the enumeration of the
+    /// word lengths is returned as well, e. g. `(3,5)`, or `(5-4)` for `Jean-Paul`.
+    pub fn normalize_phrase(self, phrase: String) -> Option<(CVec, Option<String>)> {
+        let mut word = CVec::new();
+        let mut enumeration = String::new();
+        let mut count = 0;
+        for part in phrase.split_whitespace() {
+            for (i, subpart) in part.split('-').enumerate() {
+                let letters = match self.convert(subpart) {
+                    Some(letters) => letters,
+                    None => return None,
+                };
+                if count > 0 {
+                    enumeration.push(if i == 0 { ',' } else { '-' });
+                }
+                enumeration.push_str(&letters.len().to_string());
+                count += 1;
+                word.extend(letters);
             }
         }
+        if word.len() < 2 {
+            None
+        } else if count > 1 {
+            Some((word, Some(format!("({})", enumeration))))
+        } else {
+            Some((word, None))
+        }
+    }
+
+    /// Return the single word as a char vector in upper case, normalized according to this
+    /// setting, or `None` if it is empty or contains anything but letters.
+    fn convert(self, string_word: &str) -> Option<CVec> {
+        let upper = string_word.to_uppercase();
+        let word: CVec = match self {
+            Normalization::Keep => upper.chars().collect(),
+            Normalization::Transliterate => upper
+                .replace("Ä", "AE")
+                .replace("Ö", "OE")
+                .replace("Ü", "UE").chars().collect(),
+            Normalization::Fold => upper.chars().flat_map(|c| match fold_letter(c) {
+                Some(folded) => folded.chars().collect(),
+                None => vec!(c),
+            }).collect(),
+        };
+        let valid_letter = |c: char| match self {
+            Normalization::Transliterate => c.is_alphabetic() && c.is_ascii(),
+            _ => c.is_alphabetic(),
+        };
+        if word.iter().all(|&c| valid_letter(c)) && !word.is_empty() {
+            Some(word)
+        } else {
+            None
+        }
+    }
+}
+
+/// The original spellings of normalized words, e. g. `Übermut` for `UEBERMUT`, and the word
+/// lengths of phrases, e. g. `(3,5)` for `ICECREAM`.
+pub struct Spellings {
+    spellings: HashMap<CVec, Vec<String>>,
+    enumerations: HashMap<CVec, String>,
+}
+
+impl Spellings {
+    /// Creates an empty mapping.
+    pub fn new() -> Spellings {
+        Spellings {
+            spellings: HashMap::new(),
+            enumerations: HashMap::new(),
+        }
+    }
+
+    /// Normalizes the word and records its original spelling. If `phrases` is `true`, phrases of
+    /// several words are accepted, too. Returns the normalized word, or `None` if it is not valid.
+    pub fn add(&mut self, string_word: String, normalization: Normalization, phrases: bool)
+            -> Option<CVec> {
+        let normalized = if phrases {
+            normalization.normalize_phrase(string_word.clone())
+        } else {
+            normalization.normalize(string_word.clone()).map(|word| (word, None))
+        };
+        let (word, enumeration) = match normalized {
+            Some(normalized) => normalized,
+            None => return None,
+        };
+        let spelling = string_word.split_whitespace().collect::<Vec<_>>().join(" ");
+        if let Some(enumeration) = enumeration {
+            // Keep the first enumeration, even if an earlier spelling had none.
+            self.enumerations.entry(word.clone()).or_insert(enumeration);
+        }
+        let spellings = self.spellings.entry(word.clone()).or_insert(Vec::new());
+        if !spellings.contains(&spelling) {
+            spellings.push(spelling);
+        }
+        Some(word)
+    }
+
+    /// Returns all recorded spellings of the normalized word, in the order they were added.
+    pub fn get(&self, word: &CVec) -> &[String] {
+        self.spellings.get(word).map_or(&[], |spellings| &spellings[..])
+    }
+
+    /// Returns the first recorded spelling of the normalized word or, if there is none, the word
+    /// itself.
+    pub fn display(&self, word: &CVec) -> String {
+        self.get(word).first().cloned().unwrap_or_else(|| word.iter().cloned().collect())
+    }
+
+    /// Returns the lengths of the words if the normalized word was a phrase, e. g. `(3,5)`.
+    pub fn enumeration(&self, word: &CVec) -> Option<&str> {
+        self.enumerations.get(word).map(|enumeration| &enumeration[..])
+    }
+
+    /// Writes the spellings and enumerations in the compiled dictionary format, sorted by word so
+    /// that the output doesn't depend on the order of the hash map.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<()> {
+        try!(write_u32(writer, self.spellings.len() as u32));
+        let mut entries: Vec<_> = self.spellings.iter().collect();
+        entries.sort();
+        for (word, spellings) in entries {
+            try!(write_chars(writer, word));
+            try!(write_u32(writer, spellings.len() as u32));
+            for spelling in spellings {
+                try!(write_string(writer, spelling));
+            }
+            try!(write_string(writer, self.enumeration(word).unwrap_or("")));
+        }
+        Ok(())
+    }
+
+    /// Reads the spellings and enumerations in the compiled dictionary format.
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Spellings> {
+        let mut result = Spellings::new();
+        for _ in 0..try!(read_u32(reader)) {
+            let word = try!(read_chars(reader));
+            let count = try!(read_u32(reader));
+            let spellings = try!((0..count).map(|_| read_string(reader))
+                .collect::<Result<Vec<_>>>());
+            let enumeration = try!(read_string(reader));
+            if !enumeration.is_empty() {
+                result.enumerations.insert(word.clone(), enumeration);
+            }
+            result.spellings.insert(word, spellings);
+        }
+        Ok(result)
     }
 }
 
 /// A `Dict` stores a list of words - represented as char vectors - and indexes them for
-/// efficiently iterating over all words satisfying a given `WordConstraint`.
+/// efficiently iterating over all words satisfying a given `WordConstraint`. The words are ordered
+/// by descending score, so that the iterators return the best words first.
 pub struct Dict {
     words: Vec<CVec>,
+    scores: Vec<u32>,
     lists: HashMap<WordConstraint, Vec<usize>>,
     max_n: usize,
     empty_list: Vec<usize>,
@@ -51,48 +302,162 @@ pub struct Dict {
 
 impl Dict {
     /// Return a `HashSet` of the given words, as char vectors, replacing umlauts with
-    /// corresponding diphthongs and deduplicating the words.
+    /// corresponding diphthongs and deduplicating the words. Words with other non-ASCII letters
+    /// are left out.
+    #[cfg(test)]
     pub fn to_cvec_set<T: Iterator<Item = String>>(string_words: T) -> HashSet<CVec> {
         string_words.filter_map(Dict::normalize_word).collect()
     }
 
-    /// Create a new `Dict` from the given sequence of words.
-    pub fn new<'a, T: Iterator<Item = &'a CVec>>(all_words: T) -> Dict {
+    /// Create a new `Dict` from the given sequence of words, all with the default score. The words
+    /// are shuffled using the given random number generator, so that the same generator state
+    /// always yields the same order.
+    #[cfg(test)]
+    pub fn new<'a, T: Iterator<Item = &'a CVec>, R: Rng>(all_words: T, rng: &mut R) -> Dict {
+        Dict::with_scores(all_words.map(|word| (word, DEFAULT_SCORE)), rng)
+    }
+
+    /// Create a new `Dict` from the given sequence of words and their scores. The words are
+    /// ordered by descending score, and words with the same score are shuffled using the given
+    /// random number generator.
+    pub fn with_scores<'a, T: Iterator<Item = (&'a CVec, u32)>, R: Rng>(all_words: T,
+                                                                       rng: &mut R) -> Dict {
+        let scored_words: Vec<(&CVec, u32)> = all_words.collect();
+        let order = Dict::shuffled_order(&scored_words, rng);
         let mut dict = Dict {
-            words: all_words.cloned().collect(),
+            words: order.iter().map(|&i| scored_words[i].0.clone()).collect(),
+            scores: order.iter().map(|&i| scored_words[i].1).collect(),
             lists: HashMap::new(),
             max_n: 3, // TODO: Make this a parameter?
             empty_list: Vec::new(),
         };
-        let mut rng = rand::thread_rng(); // TODO: Make this a parameter
-        rng.shuffle(&mut dict.words[..]);
-        for (i, word) in dict.words.iter().enumerate() {
-            for woco in WordConstraint::all(word, dict.max_n){
-                if !dict.lists.get(&woco).is_some() {
-                    dict.lists.insert(woco.clone(), vec!(i));
+        dict.index_words();
+        dict
+    }
+
+    /// Returns the indices of the words in the order in which they are stored: by descending score,
+    /// and shuffled using the given random number generator if the scores are equal. The order
+    /// only depends on the generator state, not on the order of the input.
+    fn shuffled_order<R: Rng>(scored_words: &[(&CVec, u32)], rng: &mut R) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..scored_words.len()).collect();
+        order.sort_by_key(|&i| scored_words[i]);
+        rng.shuffle(&mut order[..]);
+        // The sort is stable, so the words with equal scores remain shuffled.
+        order.sort_by(|&i, &j| scored_words[j].1.cmp(&scored_words[i].1));
+        order
+    }
+
+    /// Reorder the words in the same way as `with_scores` would with the given random number
+    /// generator, without rebuilding the index.
+    pub fn shuffle<R: Rng>(&mut self, rng: &mut R) {
+        let order = {
+            let scored_words: Vec<(&CVec, u32)> = self.words.iter().zip(self.scores.iter().cloned())
+                .collect();
+            Dict::shuffled_order(&scored_words, rng)
+        };
+        let mut new_index = vec!(0; order.len());
+        for (i, &j) in order.iter().enumerate() {
+            new_index[j] = i;
+        }
+        self.words = order.iter().map(|&i| self.words[i].clone()).collect();
+        self.scores = order.iter().map(|&i| self.scores[i]).collect();
+        for list in self.lists.values_mut() {
+            for i in list.iter_mut() {
+                *i = new_index[*i];
+            }
+            list.sort();
+        }
+    }
+
+    /// Write the words, their scores and the index in the compiled dictionary format. The lists are
+    /// sorted by constraint so that the output doesn't depend on the order of the hash map.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<()> {
+        try!(write_u32(writer, self.max_n as u32));
+        try!(write_u32(writer, self.words.len() as u32));
+        for (word, &score) in self.words.iter().zip(self.scores.iter()) {
+            try!(write_chars(writer, word));
+            try!(write_u32(writer, score));
+        }
+        try!(write_u32(writer, self.lists.len() as u32));
+        let mut lists: Vec<_> = self.lists.iter().collect();
+        lists.sort();
+        for (wc, list) in lists {
+            try!(wc.write_to(writer));
+            try!(write_u32(writer, list.len() as u32));
+            for &i in list {
+                try!(write_u32(writer, i as u32));
+            }
+        }
+        Ok(())
+    }
+
+    /// Read a dictionary in the compiled format.
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Dict> {
+        let mut dict = Dict {
+            words: Vec::new(),
+            scores: Vec::new(),
+            lists: HashMap::new(),
+            max_n: try!(read_u32(reader)) as usize,
+            empty_list: Vec::new(),
+        };
+        for _ in 0..try!(read_u32(reader)) {
+            dict.words.push(try!(read_chars(reader)));
+            dict.scores.push(try!(read_u32(reader)));
+        }
+        for _ in 0..try!(read_u32(reader)) {
+            let wc = try!(WordConstraint::read_from(reader));
+            let len = try!(read_u32(reader));
+            let mut list = Vec::new();
+            for _ in 0..len {
+                let i = try!(read_u32(reader)) as usize;
+                if i >= dict.words.len() {
+                    return Err(invalid_data(format!("invalid word index {}", i)));
+                }
+                // The lists are intersected by galloping search, which needs them sorted.
+                if list.last().map_or(false, |&prev| prev >= i) {
+                    return Err(invalid_data(format!("word index {} out of order", i)));
+                }
+                list.push(i);
+            }
+            dict.lists.insert(wc, list);
+        }
+        Ok(dict)
+    }
+
+    /// Build the lists of words satisfying each `WordConstraint`.
+    fn index_words(&mut self) {
+        self.lists.clear();
+        for (i, word) in self.words.iter().enumerate() {
+            for woco in WordConstraint::all(word, self.max_n){
+                if !self.lists.get(&woco).is_some() {
+                    self.lists.insert(woco.clone(), vec!(i));
                 } else {
-                    dict.lists.get_mut(&woco).unwrap().push(i);
+                    self.lists.get_mut(&woco).unwrap().push(i);
                 }
             }
         }
-        dict
     }
 
-    fn normalize_word(string_word: String) -> Option<CVec> {
-        // TODO: Use to_uppercase() once it's stable.
-        let word: CVec = string_word.to_ascii_uppercase().trim()
-                       .replace("ä", "AE")
-                       .replace("Ä", "AE")
-                       .replace("ö", "OE")
-                       .replace("Ö", "OE")
-                       .replace("ü", "UE")
-                       .replace("Ü", "UE")
-                       .replace("ß", "SS").chars().collect();
-        if word.iter().all(|&c| c.is_alphabetic() && c.is_ascii()) && word.len() > 1 {
-            Some(word)
-        } else {
-            None
+    /// Remove the given words from this dictionary, keeping the order of the others. Returns the
+    /// number of removed words.
+    pub fn exclude(&mut self, excluded: &HashSet<CVec>) -> usize {
+        let len = self.words.len();
+        let (words, scores): (Vec<CVec>, Vec<u32>) = self.words.drain(..).zip(self.scores.drain(..))
+            .filter(|&(ref word, _)| !excluded.contains(word))
+            .unzip();
+        self.words = words;
+        self.scores = scores;
+        if self.words.len() < len {
+            self.index_words();
         }
+        len - self.words.len()
+    }
+
+    /// Return the word as a char vector in upper case, with umlauts replaced by diphthongs, or
+    /// `None` if it contains anything but ASCII letters and umlauts or is shorter than two letters.
+    #[cfg(test)]
+    pub fn normalize_word(string_word: String) -> Option<CVec> {
+        Normalization::Transliterate.normalize(string_word)
     }
 
     fn get_list<'a>(&'a self, wc: &WordConstraint) -> &'a Vec<usize> {
@@ -104,17 +469,26 @@ impl Dict {
         self.matching_words(word.clone()).next().is_some()
     }
 
+    /// Return the score of the given word, or `None` if it is not in this dictionary.
+    pub fn score(&self, word: &CVec) -> Option<u32> {
+        self.matching_words(word.clone()).next_scored().map(|(_, score)| score)
+    }
+
+    /// Return an iterator over the constraints and the indices of the words satisfying them.
+    pub fn constraint_lists<'a>(&'a self) -> hash_map::Iter<'a, WordConstraint, Vec<usize>> {
+        self.lists.iter()
+    }
+
     /// Return an iterator over all words in the dictionary.
     pub fn all_words<'a>(&'a self) -> slice::Iter<'a, CVec> {
         self.words.iter()
     }
 
-    fn get_matching_word_list<'a>(&'a self, pattern: &CVec) -> &'a Vec<usize> {
+    /// Return the lists of words satisfying the constraints that together cover all letters of the
+    /// pattern, sorted by length. A word matches the pattern if and only if it is in all of them.
+    fn get_matching_word_lists<'a>(&'a self, pattern: &CVec) -> Vec<&'a Vec<usize>> {
         let len = pattern.len();
-        let mut list: &'a Vec<usize> = self.get_list(&WordConstraint::Length(pattern.len()));
-        if list.len() == 0 {
-            return list;
-        }
+        let mut lists = Vec::new();
         let mut pos = 0;
         for i in pattern.iter().enumerate()
                 .filter(|&(_, ch)| ch == &BLOCK)
@@ -123,48 +497,289 @@ impl Dict {
             if i > pos {
                 let subword = &pattern[pos..i];
                 let n = cmp::min(self.max_n, subword.len());
-                for dp in 1..(subword.len() - n) {
+                // Non-overlapping n-grams, and one that ends with the last letter.
+                let starts = (0..(subword.len() - n)).filter(|dp| dp % n == 0)
+                    .chain(Some(subword.len() - n));
+                for dp in starts {
                     let wc = WordConstraint::with_ngram(&subword[dp..(dp + n)], pos + dp, len);
-                    let new_list = self.get_list(&wc);
-                    if list.len() > new_list.len() {
-                        list = new_list;
-                        if list.len() == 0 {
-                            return list;
-                        }
-                    }
+                    lists.push(self.get_list(&wc));
                 }
             }
             pos = i + 1;
         }
-        list
+        if lists.is_empty() {
+            lists.push(self.get_list(&WordConstraint::Length(len)));
+        }
+        lists.sort_by_key(|list| list.len());
+        lists
     }
 
     /// Return an iterator over all words in the dictionary matching the given pattern.
     pub fn matching_words<'a>(&'a self, pattern: CVec) -> PatternIter<'a> {
-        let list = self.get_matching_word_list(&pattern);
+        let mut lists = self.get_matching_word_lists(&pattern).into_iter();
+        let list = lists.next().unwrap();
         PatternIter {
             dict: self,
-            pattern: pattern,
-            list: list,
+            list: &list[..],
             index: 0,
+            others: lists.map(|list| (&list[..], 0)).collect(),
         }
     }
 }
 
 #[cfg(test)]
-mod tests {
+pub mod tests {
     use super::*;
+    use cw::{CVec, BLOCK};
+    use rand::{Rng, SeedableRng, StdRng, thread_rng};
+    use std::collections::HashSet;
+    use std::time::Instant;
 
     #[test]
     fn test() {
         let words_vec = vec!("FOO", "FOOBAR", "FOE", "TOE");
         let words = Dict::to_cvec_set(words_vec.into_iter().map(|s| s.to_string()));
-        let dict = Dict::new(words.iter());
+        let dict = Dict::new(words.iter(), &mut thread_rng());
         assert_eq!(2, dict.matching_words("#OE".chars().collect()).count());
         assert_eq!(1, dict.matching_words("F#E".chars().collect()).count());
         assert_eq!(0, dict.matching_words("T#O".chars().collect()).count());
         assert_eq!(0, dict.matching_words("F###".chars().collect()).count());
         assert_eq!(0, dict.matching_words("##".chars().collect()).count());
     }
-}
 
+    /// Returns the given number of random words with typical letter frequencies.
+    pub fn random_words(count: usize, seed: usize) -> HashSet<CVec> {
+        let letters: CVec = "EEEEEEAAAAIIIINNNNRRRSSSTTTLLOOUUCDGHMBFKPVWZ".chars().collect();
+        let mut rng: StdRng = SeedableRng::from_seed(&[seed][..]);
+        (0..count).map(|_| {
+            let len = rng.gen_range(3, 11);
+            (0..len).map(|_| letters[rng.gen_range(0, letters.len())]).collect()
+        }).collect()
+    }
+
+    /// Returns patterns from the given words, with some letters replaced by blanks.
+    fn random_patterns(words: &HashSet<CVec>, count: usize, seed: usize) -> Vec<CVec> {
+        let mut rng: StdRng = SeedableRng::from_seed(&[seed][..]);
+        words.iter().take(count).map(|word| word.iter().map(|&c| {
+            if rng.gen_range(0, 3) == 0 { c } else { BLOCK }
+        }).collect()).collect()
+    }
+
+    /// Returns the indices of the matching words by scanning the whole dictionary.
+    fn naive_matches(dict: &Dict, pattern: &CVec) -> Vec<usize> {
+        dict.all_words().enumerate().filter(|&(_, word)| {
+            word.len() == pattern.len()
+                && word.iter().zip(pattern.iter()).all(|(&cw, &cp)| cw == cp || cp == BLOCK)
+        }).map(|(i, _)| i).collect()
+    }
+
+    #[test]
+    fn test_gallop() {
+        let list = vec!(1, 3, 5, 7, 9, 11, 13, 15, 17);
+        assert_eq!(0, gallop(&list, 0, 0));
+        assert_eq!(2, gallop(&list, 0, 5));
+        assert_eq!(3, gallop(&list, 2, 6));
+        assert_eq!(8, gallop(&list, 1, 17));
+        assert_eq!(9, gallop(&list, 4, 18));
+        assert_eq!(9, gallop(&list, 9, 1));
+        assert_eq!(5, gallop(&list, 5, 1));
+    }
+
+    #[test]
+    fn test_matching_words() {
+        let words = random_words(2000, 1);
+        let dict = Dict::new(words.iter(), &mut thread_rng());
+        for pattern in random_patterns(&words, 200, 2) {
+            let expected: Vec<&CVec> = naive_matches(&dict, &pattern).into_iter()
+                .map(|i| &dict.words[i]).collect();
+            assert_eq!(expected, dict.matching_words(pattern).collect::<Vec<_>>());
+        }
+    }
+
+    /// Compares the time it takes to find all matches of random patterns in a dictionary with
+    /// 100000 words, using the intersection of all constraint lists, and scanning only the
+    /// smallest list. Run with `cargo test --release bench_matching_words -- --ignored
+    /// --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_matching_words() {
+        let words = random_words(100000, 3);
+        let dict = Dict::new(words.iter(), &mut thread_rng());
+        let patterns = random_patterns(&words, 10000, 4);
+        let start = Instant::now();
+        let count: usize = patterns.iter().map(|p| dict.matching_words(p.clone()).count()).sum();
+        let intersection_time = start.elapsed();
+        let start = Instant::now();
+        let scan_count: usize = patterns.iter().map(|pattern| {
+            let list = dict.get_matching_word_lists(pattern)[0];
+            list.iter().filter(|&&i| {
+                dict.words[i].iter().zip(pattern.iter()).all(|(&cw, &cp)| cw == cp || cp == BLOCK)
+            }).count()
+        }).sum();
+        let scan_time = start.elapsed();
+        assert_eq!(scan_count, count);
+        println!("{} matches. Intersection: {:?}, scanning the smallest list: {:?}",
+                 count, intersection_time, scan_time);
+    }
+
+    #[test]
+    fn test_seed() {
+        let words_vec = vec!("FOO", "BAR", "BAZ", "QUX", "FOE", "TOE", "TOO");
+        let words = Dict::to_cvec_set(words_vec.into_iter().map(|s| s.to_string()));
+        let mut rng0: StdRng = SeedableRng::from_seed(&[42][..]);
+        let mut rng1: StdRng = SeedableRng::from_seed(&[42][..]);
+        let dict0 = Dict::new(words.iter(), &mut rng0);
+        let dict1 = Dict::new(words.iter(), &mut rng1);
+        assert!(dict0.all_words().eq(dict1.all_words()));
+    }
+
+    #[test]
+    fn test_scores() {
+        let words: Vec<CVec> = vec!("FOO", "FOE", "TOE", "TOO").into_iter()
+            .map(|s| s.chars().collect()).collect();
+        let scores = vec!(10, 60, 30, 60);
+        let dict = Dict::with_scores(words.iter().zip(scores), &mut thread_rng());
+        let found: Vec<(String, u32)> = {
+            let mut iter = dict.matching_words("###".chars().collect());
+            let mut found = Vec::new();
+            while let Some((word, score)) = iter.next_scored() {
+                found.push((word.iter().cloned().collect(), score));
+            }
+            found
+        };
+        assert_eq!(vec!(60, 60, 30, 10), found.iter().map(|&(_, score)| score).collect::<Vec<_>>());
+        assert_eq!("TOE", found[2].0);
+        assert_eq!(Some(30), dict.score(&words[2]));
+        assert_eq!(None, dict.score(&"BAR".chars().collect()));
+        assert_eq!(("foo bar", Some(42)), split_score("foo bar;42"));
+        assert_eq!(("foo;bar", None), split_score("foo;bar"));
+        assert_eq!(("foo", None), split_score("foo"));
+    }
+
+    #[test]
+    fn test_shuffle() {
+        let words: Vec<CVec> = vec!("FOO", "FOE", "TOE", "TOO", "BAR", "BAZ").into_iter()
+            .map(|s| s.chars().collect()).collect();
+        let scores = vec!(10, 60, 30, 60, 60, 10);
+        let mut rng0: StdRng = SeedableRng::from_seed(&[42][..]);
+        let mut rng1: StdRng = SeedableRng::from_seed(&[42][..]);
+        let dict0 = Dict::with_scores(words.iter().zip(scores.iter().cloned()), &mut rng0);
+        let mut dict1 = Dict::with_scores(words.iter().zip(scores.iter().cloned()).rev(),
+                                          &mut thread_rng());
+        dict1.shuffle(&mut rng1);
+        assert!(dict0.all_words().eq(dict1.all_words()));
+        let pattern: CVec = "#O#".chars().collect();
+        assert!(dict0.matching_words(pattern.clone()).eq(dict1.matching_words(pattern)));
+        assert_eq!(Some(30), dict1.score(&words[2]));
+    }
+
+    #[test]
+    fn test_exclude() {
+        let words = Dict::to_cvec_set(vec!("FOO", "FOE", "TOE").into_iter()
+            .map(|s| s.to_string()));
+        let mut dict = Dict::new(words.iter(), &mut thread_rng());
+        let excluded = Dict::to_cvec_set(vec!("FOE", "BAR").into_iter().map(|s| s.to_string()));
+        assert_eq!(1, dict.exclude(&excluded));
+        assert_eq!(2, dict.all_words().count());
+        assert!(!dict.contains(&"FOE".chars().collect()));
+        assert_eq!(1, dict.matching_words("#OE".chars().collect()).count());
+        assert_eq!(Some(DEFAULT_SCORE), dict.score(&"FOO".chars().collect()));
+        assert_eq!(0, dict.exclude(&excluded));
+    }
+
+    #[test]
+    fn test_read_unsorted_list() {
+        let compiled = |list: &[u32]| {
+            let mut bytes = Vec::new();
+            write_u32(&mut bytes, 1).unwrap(); // max_n
+            write_u32(&mut bytes, 2).unwrap();
+            for word in &["AB", "CD"] {
+                write_chars(&mut bytes, &word.chars().collect::<CVec>()).unwrap();
+                write_u32(&mut bytes, DEFAULT_SCORE).unwrap();
+            }
+            write_u32(&mut bytes, 1).unwrap();
+            WordConstraint::Length(2).write_to(&mut bytes).unwrap();
+            write_u32(&mut bytes, list.len() as u32).unwrap();
+            for &i in list {
+                write_u32(&mut bytes, i).unwrap();
+            }
+            bytes
+        };
+        let dict = Dict::read_from(&mut &compiled(&[0, 1])[..]).unwrap();
+        assert_eq!(2, dict.matching_words("##".chars().collect()).count());
+        assert!(Dict::read_from(&mut &compiled(&[1, 0])[..]).is_err());
+        assert!(Dict::read_from(&mut &compiled(&[0, 0])[..]).is_err());
+        assert!(Dict::read_from(&mut &compiled(&[0, 2])[..]).is_err());
+    }
+
+    #[test]
+    fn test_spellings() {
+        let mut spellings = Spellings::new();
+        let word: CVec = "UEBERMUT".chars().collect();
+        let tr = Normalization::Transliterate;
+        assert_eq!(Some(word.clone()), spellings.add("Übermut".to_string(), tr, false));
+        assert_eq!(Some(word.clone()), spellings.add(" übermut".to_string(), tr, false));
+        assert_eq!(Some(word.clone()), spellings.add("Übermut".to_string(), tr, false));
+        assert_eq!(None, spellings.add("Foo bar".to_string(), tr, false));
+        assert_eq!(&["Übermut".to_string(), "übermut".to_string()][..], spellings.get(&word));
+        assert_eq!("Übermut", spellings.display(&word));
+        assert_eq!("FOO", spellings.display(&"FOO".chars().collect()));
+        assert_eq!(None, spellings.enumeration(&word));
+        let phrase: CVec = "ICECREAM".chars().collect();
+        assert_eq!(Some(phrase.clone()), spellings.add("ice  cream".to_string(), tr, true));
+        assert_eq!(Some(phrase.clone()), spellings.add("icecream".to_string(), tr, true));
+        assert_eq!("ice cream", spellings.display(&phrase));
+        assert_eq!(Some("(3,5)"), spellings.enumeration(&phrase));
+        // A phrase added after the single word still has an enumeration.
+        let name: CVec = "NEWYORK".chars().collect();
+        assert_eq!(Some(name.clone()), spellings.add("Newyork".to_string(), tr, true));
+        assert_eq!(Some(name.clone()), spellings.add("New York".to_string(), tr, true));
+        assert_eq!(Some(name.clone()), spellings.add("New-York".to_string(), tr, true));
+        assert_eq!(Some("(3,4)"), spellings.enumeration(&name));
+    }
+
+    #[test]
+    fn test_phrases() {
+        let normalize = |phrase: &str| {
+            Normalization::Transliterate.normalize_phrase(phrase.to_string())
+                .map(|(cvec, enumeration)| (cvec.into_iter().collect::<String>(), enumeration))
+        };
+        assert_eq!(Some(("NEWYORK".to_string(), Some("(3,4)".to_string()))),
+                   normalize("New York"));
+        assert_eq!(Some(("JEANPAULSARTRE".to_string(), Some("(4-4,6)".to_string()))),
+                   normalize(" Jean-Paul Sartre "));
+        assert_eq!(Some(("UEBERMUT".to_string(), None)), normalize("Übermut"));
+        assert_eq!(Some(("ALACARTE".to_string(), Some("(1,2,5)".to_string()))),
+                   normalize("a la carte"));
+        assert_eq!(None, normalize("foo--bar"));
+        assert_eq!(None, normalize("-foo"));
+        assert_eq!(None, normalize("rock 'n' roll"));
+        assert_eq!(None, normalize("a"));
+    }
+
+    #[test]
+    fn test_normalization() {
+        let normalize = |normalization: Normalization, word: &str| {
+            normalization.normalize(word.to_string()).map(|cvec| cvec.into_iter().collect())
+        };
+        let (keep, tr, fold) =
+            (Normalization::Keep, Normalization::Transliterate, Normalization::Fold);
+        assert_eq!(Some("STRASSE".to_string()), normalize(tr, "Straße"));
+        assert_eq!(Some("UEBERMUT".to_string()), normalize(tr, "übermut"));
+        assert_eq!(None, normalize(tr, "ñandú"));
+        assert_eq!(Some("ÑANDÚ".to_string()), normalize(keep, "ñandú"));
+        assert_eq!(Some("NANDU".to_string()), normalize(fold, "ñandú"));
+        assert_eq!(Some("ÜBERMUT".to_string()), normalize(keep, "übermut"));
+        assert_eq!(Some("UBERMUT".to_string()), normalize(fold, "übermut"));
+        assert_eq!(Some("ŁÓDŹ".to_string()), normalize(keep, "Łódź"));
+        assert_eq!(Some("LODZ".to_string()), normalize(fold, "Łódź"));
+        assert_eq!(Some("ΛΌΓΟΣ".to_string()), normalize(keep, "λόγος"));
+        assert_eq!(Some("КОТ".to_string()), normalize(fold, "кот"));
+        assert_eq!(Some("BLÅBÆR".to_string()), normalize(keep, "blåbær"));
+        assert_eq!(Some("BLABAER".to_string()), normalize(fold, "blåbær"));
+        assert_eq!(None, normalize(keep, "x"));
+        assert_eq!(None, normalize(fold, "foo-bar"));
+        assert_eq!(Some(fold), Normalization::from_name("fold"));
+        assert_eq!(None, Normalization::from_name("foo"));
+    }
+}
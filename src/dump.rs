@@ -0,0 +1,401 @@
+//! Hints from a local Wikipedia or Wiktionary dump, e. g. `enwiki-latest-pages-articles.xml`.
+//!
+//! The dump is read once, and the lead sentence of each article whose title is one of the given
+//! words is written to a clue file that can be read with the `--clues` option, e. g.:
+//!
+//! ```text
+//! RUST	a programming language		en
+//! SERVO	a parallel layout engine		en
+//! ```
+//!
+//! Redirections are followed if the target article comes after the redirection in the dump.
+use cw::CVec;
+use dict::Normalization;
+use get_hints::extract_hint;
+use hint_rules::HintRules;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Error, ErrorKind, Lines, Read, Result, Write};
+use std::process::{Child, Command, Stdio};
+
+/// A page from the dump.
+pub struct Page {
+    pub title: String,
+    /// The namespace: 0 for articles.
+    pub ns: u32,
+    /// The title of the page this one redirects to, if any.
+    pub redirect: Option<String>,
+    pub text: String,
+}
+
+/// Replaces the XML entities in the text with the characters they represent.
+fn unescape(text: &str) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = match rest.find(';') {
+            Some(end) => end,
+            None => break,
+        };
+        let entity = &rest[1..end];
+        let c = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ if entity.starts_with("#x") => u32::from_str_radix(&entity[2..], 16).ok()
+                .and_then(::std::char::from_u32),
+            _ if entity.starts_with('#') => entity[1..].parse().ok()
+                .and_then(::std::char::from_u32),
+            _ => None,
+        };
+        match c {
+            Some(c) => {
+                result.push(c);
+                rest = &rest[(end + 1)..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Returns the content of the element on the line, e. g. `Foo` for `<title>Foo</title>`.
+fn element_content<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+    let start_tag = format!("<{}>", name);
+    let end_tag = format!("</{}>", name);
+    let line = line.trim();
+    if line.starts_with(&start_tag) && line.ends_with(&end_tag) {
+        Some(&line[start_tag.len()..(line.len() - end_tag.len())])
+    } else {
+        None
+    }
+}
+
+/// An iterator over the pages of a MediaWiki XML dump. It expects each element other than the
+/// page text to be on a line of its own, as in the dumps published by the Wikimedia Foundation.
+pub struct DumpReader<R: BufRead> {
+    lines: Lines<R>,
+}
+
+impl<R: BufRead> DumpReader<R> {
+    /// Creates an iterator over the pages in the given dump.
+    pub fn new(reader: R) -> DumpReader<R> {
+        DumpReader { lines: reader.lines() }
+    }
+
+    /// Reads the page text, starting with the line containing the `text` start tag.
+    fn read_text(&mut self, line: &str) -> Result<String> {
+        let start = line.find("<text").unwrap();
+        let tag_end = match line[start..].find('>') {
+            Some(tag_end) => start + tag_end,
+            None => return Err(Error::new(ErrorKind::InvalidData, "unterminated text tag")),
+        };
+        if line[..tag_end].ends_with('/') {
+            return Ok(String::new());
+        }
+        let mut text = line[(tag_end + 1)..].to_string();
+        while !text.contains("</text>") {
+            match self.lines.next() {
+                Some(line) => {
+                    text.push('\n');
+                    text.push_str(&try!(line));
+                }
+                None => return Err(Error::new(ErrorKind::UnexpectedEof, "unterminated text")),
+            }
+        }
+        let end = text.find("</text>").unwrap();
+        text.truncate(end);
+        Ok(unescape(&text))
+    }
+
+    /// Reads the next page, or returns `None` at the end of the dump.
+    fn read_page(&mut self) -> Result<Option<Page>> {
+        let mut page = None;
+        while let Some(line) = self.lines.next() {
+            let line = try!(line);
+            let trimmed = line.trim();
+            if trimmed == "<page>" {
+                page = Some(Page {
+                    title: String::new(),
+                    ns: 0,
+                    redirect: None,
+                    text: String::new(),
+                });
+                continue;
+            }
+            let page = match page.as_mut() {
+                Some(page) => page,
+                None => continue, // Skip the site info.
+            };
+            if trimmed == "</page>" {
+                break;
+            } else if let Some(title) = element_content(trimmed, "title") {
+                page.title = unescape(title);
+            } else if let Some(ns) = element_content(trimmed, "ns") {
+                page.ns = try!(ns.parse().map_err(|_| {
+                    Error::new(ErrorKind::InvalidData, format!("invalid namespace {}", ns))
+                }));
+            } else if trimmed.starts_with("<redirect title=\"") {
+                let title = &trimmed["<redirect title=\"".len()..];
+                page.redirect = title.find('"').map(|end| unescape(&title[..end]));
+            } else if trimmed.starts_with("<text") {
+                page.text = try!(self.read_text(&line));
+            }
+        }
+        Ok(page)
+    }
+}
+
+impl<R: BufRead> Iterator for DumpReader<R> {
+    type Item = Result<Page>;
+
+    fn next(&mut self) -> Option<Result<Page>> {
+        match self.read_page() {
+            Ok(Some(page)) => Some(Ok(page)),
+            Ok(None) => None,
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+/// A dump file, possibly decompressed by a child process. If that fails, reading from the file
+/// returns an error at the end of the output.
+pub struct DumpFile {
+    reader: BufReader<Box<Read>>,
+    child: Option<Child>,
+}
+
+impl DumpFile {
+    /// Waits for the decompression to finish, and returns an error if it failed.
+    fn wait_for_child(&mut self) -> Result<()> {
+        if let Some(mut child) = self.child.take() {
+            let status = try!(child.wait());
+            if !status.success() {
+                return Err(Error::new(ErrorKind::InvalidData,
+                                      format!("decompressing the dump failed: {}", status)));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Read for DumpFile {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let len = try!(self.reader.read(buf));
+        if len == 0 && !buf.is_empty() {
+            try!(self.wait_for_child());
+        }
+        Ok(len)
+    }
+}
+
+impl BufRead for DumpFile {
+    fn fill_buf(&mut self) -> Result<&[u8]> {
+        if try!(self.reader.fill_buf()).is_empty() {
+            try!(self.wait_for_child());
+        }
+        self.reader.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.reader.consume(amt)
+    }
+}
+
+/// Opens the dump file with the given name. If it ends in `.bz2`, it is decompressed using the
+/// `bzip2` program.
+pub fn open_dump(filename: &str) -> Result<DumpFile> {
+    if filename.ends_with(".bz2") {
+        let mut child = try!(Command::new("bzip2")
+            .args(&["-dc", filename])
+            .stdout(Stdio::piped())
+            .spawn());
+        let stdout = child.stdout.take().unwrap();
+        Ok(DumpFile { reader: BufReader::new(Box::new(stdout)), child: Some(child) })
+    } else {
+        let file = try!(File::open(filename));
+        Ok(DumpFile { reader: BufReader::new(Box::new(file)), child: None })
+    }
+}
+
+/// Writes the field, quoted if necessary so that `ClueDb` can read it.
+fn write_field<W: Write>(writer: &mut W, field: &str) -> Result<()> {
+    if field.contains('\t') || field.starts_with('"') {
+        write!(writer, "\"{}\"", field.replace("\"", "\"\""))
+    } else {
+        write!(writer, "{}", field)
+    }
+}
+
+/// Adds the hints extracted from the page for each of the words with the given titles, unless
+/// they already have one.
+fn add_hints(hints: &mut BTreeMap<CVec, String>, found: Vec<(CVec, String)>, page: &Page,
+             rules: &HintRules) {
+    for (word, title) in found {
+        if let Ok(hint) = extract_hint(page.text.clone(), &title, rules) {
+            hints.entry(word).or_insert(hint);
+        }
+    }
+}
+
+/// Reads the dump and writes a clue file with a hint for each of the given words that has an
+/// article about it, extracted using the given rules and tagged with the given language. The
+/// titles are normalized in the same way as the words, so that e. g. the article `Ice cream` is
+/// found for the phrase `ICECREAM`. Returns the number of hints written.
+///
+/// The dump is opened with `open`. If the targets of some redirections haven't been found after
+/// them, the dump is opened and read a second time to find the targets that come before them.
+pub fn index_dump<R, F, W>(mut open: F, words: &HashSet<CVec>, normalization: Normalization,
+                           lang: &str, rules: &HintRules, writer: &mut W) -> Result<usize>
+        where R: BufRead, F: FnMut() -> Result<R>, W: Write {
+    let mut hints = BTreeMap::new();
+    // The titles of redirection targets, and the words redirecting to them.
+    let mut redirects: HashMap<String, Vec<(CVec, String)>> = HashMap::new();
+    for page in DumpReader::new(try!(open())) {
+        let page = try!(page);
+        if page.ns != 0 {
+            continue;
+        }
+        let mut found = redirects.remove(&page.title).unwrap_or(Vec::new());
+        if let Some((word, _)) = normalization.normalize_phrase(page.title.clone()) {
+            if words.contains(&word) && !hints.contains_key(&word) {
+                match page.redirect {
+                    Some(ref target) => {
+                        redirects.entry(target.clone()).or_insert(Vec::new())
+                            .push((word, page.title.clone()));
+                    }
+                    None => found.push((word, page.title.clone())),
+                }
+            }
+        }
+        add_hints(&mut hints, found, &page, rules);
+    }
+    if !redirects.is_empty() {
+        for page in DumpReader::new(try!(open())) {
+            let page = try!(page);
+            if page.ns == 0 && page.redirect.is_none() {
+                if let Some(found) = redirects.remove(&page.title) {
+                    add_hints(&mut hints, found, &page, rules);
+                }
+            }
+        }
+    }
+    for (word, hint) in &hints {
+        try!(write_field(writer, &word.iter().cloned().collect::<String>()));
+        try!(write!(writer, "\t"));
+        try!(write_field(writer, hint));
+        try!(writeln!(writer, "\t\t{}", lang));
+    }
+    Ok(hints.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clues::{ClueDb, ClueProvider};
+    use dict::{Dict, Normalization};
+    use hint_rules::HintRules;
+
+    const DUMP: &'static str = r#"<mediawiki xml:lang="en">
+  <siteinfo>
+    <sitename>Wikipedia</sitename>
+  </siteinfo>
+  <page>
+    <title>Rust</title>
+    <ns>0</ns>
+    <redirect title="Rust (programming language)" />
+    <revision>
+      <text xml:space="preserve">#REDIRECT [[Rust (programming language)]]</text>
+    </revision>
+  </page>
+  <page>
+    <title>Servo</title>
+    <ns>0</ns>
+    <revision>
+      <text xml:space="preserve">'''Servo''' is a layout engine &amp; browser.
+It is written in &quot;Rust&quot;.</text>
+    </revision>
+  </page>
+  <page>
+    <title>Talk:Servo</title>
+    <ns>1</ns>
+    <revision>
+      <text xml:space="preserve">'''Servo''' is a talk page.</text>
+    </revision>
+  </page>
+  <page>
+    <title>Rust (programming language)</title>
+    <ns>0</ns>
+    <revision>
+      <text xml:space="preserve">'''Rust''' is a programming language.</text>
+    </revision>
+  </page>
+  <page>
+    <title>Empty</title>
+    <ns>0</ns>
+    <revision>
+      <text xml:space="preserve" />
+    </revision>
+  </page>
+  <page>
+    <title>Gecko (software)</title>
+    <ns>0</ns>
+    <revision>
+      <text xml:space="preserve">'''Gecko''' is a browser engine.</text>
+    </revision>
+  </page>
+  <page>
+    <title>Gecko</title>
+    <ns>0</ns>
+    <redirect title="Gecko (software)" />
+    <revision>
+      <text xml:space="preserve">#REDIRECT [[Gecko (software)]]</text>
+    </revision>
+  </page>
+</mediawiki>
+"#;
+
+    #[test]
+    fn test_dump_reader() {
+        let pages: Vec<Page> = DumpReader::new(DUMP.as_bytes()).map(Result::unwrap).collect();
+        assert_eq!(7, pages.len());
+        assert_eq!("Rust", pages[0].title);
+        assert_eq!(Some("Rust (programming language)".to_string()), pages[0].redirect);
+        assert_eq!("'''Servo''' is a layout engine & browser.\nIt is written in \"Rust\".",
+                   pages[1].text);
+        assert_eq!(1, pages[2].ns);
+        assert_eq!("", pages[4].text);
+        assert_eq!("<a&b>&#;&foo", unescape("&lt;a&amp;b&gt;&#;&foo"));
+        assert_eq!("ä", unescape("&#228;"));
+    }
+
+    #[test]
+    fn test_index_dump() {
+        let words = Dict::to_cvec_set(vec!("servo", "rust", "empty", "gecko", "missing")
+            .into_iter().map(|s| s.to_string()));
+        let mut output = Vec::new();
+        let rules = HintRules::for_lang("en");
+        let tr = Normalization::Transliterate;
+        let open = || Ok(DUMP.as_bytes());
+        assert_eq!(3, index_dump(open, &words, tr, "en", &rules, &mut output).unwrap());
+        let mut db = ClueDb::new();
+        db.read(&output[..], '\t').unwrap();
+        assert_eq!(Some("a layout engine & browser".to_string()),
+                   db.get_clue("SERVO", "en").unwrap());
+        assert_eq!(Some("a programming language".to_string()), db.get_clue("RUST", "en").unwrap());
+        assert_eq!(None, db.get_clue("EMPTY", "en").unwrap());
+        // The redirection's target comes first, so it is found in a second pass.
+        assert_eq!(Some("a browser engine".to_string()), db.get_clue("GECKO", "en").unwrap());
+        let mut quoted = Vec::new();
+        write_field(&mut quoted, "\"Quoted\" text").unwrap();
+        assert_eq!(&b"\"\"\"Quoted\"\" text\""[..], &quoted[..]);
+    }
+}
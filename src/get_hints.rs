@@ -1,15 +1,66 @@
 // TODO: Use the regex! macro once that feature is stable.
 // TODO: Consider using external software for that, e. g.:
 //       https://github.com/bwbaugh/wikipedia-extractor/blob/master/WikiExtractor.py
-//       It might be best to move the whole hint generation out of crosswords-rs. Instead, you
-//       could specify a hint generation program at the command line.
 use regex::Regex;
 use std::ascii::AsciiExt;
-use std::collections::HashMap;
-
-use std::io::Read;
+use std::error;
+use std::fmt;
+use std::io::{self, Read};
+use std::time::Duration;
 
+use hyper;
 use hyper::Client;
+use hyper::status::StatusCode;
+
+use hint_rules::HintRules;
+
+/// How long to wait for the server before giving up on a word.
+const TIMEOUT_SECS: u64 = 10;
+
+/// The reasons why no hint could be found for a word.
+#[derive(Debug)]
+pub enum HintError {
+    /// The request failed, e. g. because the server is unreachable or didn't respond in time.
+    Http(hyper::Error),
+    /// The response couldn't be read.
+    Io(io::Error),
+    /// The server responded with an error status other than "not found".
+    Status(StatusCode),
+    /// There is no article about the word.
+    NotFound,
+    /// The article contains no usable text.
+    NoHint,
+}
+
+impl fmt::Display for HintError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            HintError::Http(ref err) => write!(f, "request failed: {}", err),
+            HintError::Io(ref err) => write!(f, "failed to read the response: {}", err),
+            HintError::Status(status) => write!(f, "the server responded with status {}", status),
+            HintError::NotFound => write!(f, "article not found"),
+            HintError::NoHint => write!(f, "the article contains no hint"),
+        }
+    }
+}
+
+impl error::Error for HintError {
+    fn description(&self) -> &str {
+        "failed to get a hint"
+    }
+}
+
+impl From<hyper::Error> for HintError {
+    fn from(err: hyper::Error) -> HintError {
+        HintError::Http(err)
+    }
+}
+
+impl From<io::Error> for HintError {
+    fn from(err: io::Error) -> HintError {
+        HintError::Io(err)
+    }
+}
 
 fn replace_all<'a>(text: String, replacements: Vec<(&'a str, &'a str)>) -> String {
     let mut new_text = text;
@@ -20,7 +71,11 @@ fn replace_all<'a>(text: String, replacements: Vec<(&'a str, &'a str)>) -> Strin
     new_text
 }
 
-fn get_hint_from_article(article: String, word: &str, lang: &str) -> String {
+fn get_hint_from_article(article: String, word: &str, rules: &HintRules) -> String {
+    let article = match rules.removed_template_re() {
+        Some(re) => Regex::new(&re).unwrap().replace_all(&article, ""),
+        None => article,
+    };
     let clean_article = replace_all(article, vec!(
         // Remove quotations.
         (r#"<ref>.*</ref>"#, ""),
@@ -39,29 +94,26 @@ fn get_hint_from_article(article: String, word: &str, lang: &str) -> String {
         (r#"\[\[([^\]]*\|)?(?P<link>[^\|\]]*)\]\]"#, "$link"),
         // Display bold text as plain text.
         (r#"'''(?P<bold>[^']*)'''"#, "$bold"))).trim().to_string();
-    let descr_init = match lang {
-        "de" => " ist | bezeichnet | war | sind | waren ",
-        "en" => " is | are | was | were ",
-        _ => unimplemented!(),
-    };
+    // If the rules contain no copulas or disambiguation phrases, only the generic patterns are
+    // tried.
     let word_re = format!(r#"((?i){})"#, word);
     // Disambiguations:
-    let ex_re1 = Regex::new(&format!(
-            r#"{}\S* (or [^\.\n]* )?may refer to:\n(\s*((=|;).*|.*:)?\n)*\*(?P<excerpt>.*)\n"#,
-            word_re))
-        .unwrap();
+    let ex_re1 = rules.disambiguation_re().map(|disambiguation_re| Regex::new(&format!(
+            r#"{}\S* ([^\.\n:]* )?{}\s*:\n(\s*((=|;).*|.*:)?\n)*\*(?P<excerpt>.*)\n"#,
+            word_re, disambiguation_re))
+        .unwrap());
     // Sentences starting with "<word> is ...":
-    let ex_re0 = Regex::new(
+    let ex_re0 = rules.copula_re().map(|copula_re| Regex::new(
             &format!(r#"({}(\([^\)]*\))?({})(?P<excerpt>[^\."\n]*)(\.|"|\n))"#,
-                     word_re, descr_init))
-        .unwrap();
+                     word_re, copula_re))
+        .unwrap());
     // Any sentence containing the word.
     let ex_re2 = Regex::new(
         &format!(r#"(\n|\*|\. )\s*(?P<excerpt>[^\.\n]*{}[^\.\n\*]*(\.|\n))"#, word_re)).unwrap();
     // If all else fails, any sentence.
     let ex_re3 = Regex::new(r#"(\n|\. )\s*(?P<excerpt>[^\.\n]+(\.|\n))"#).unwrap();
-    let excerpt = match ex_re0.captures(&clean_article.clone())
-            .or(ex_re1.captures(&clean_article.clone()))
+    let excerpt = match ex_re0.as_ref().and_then(|re| re.captures(&clean_article))
+            .or(ex_re1.as_ref().and_then(|re| re.captures(&clean_article)))
             .or(ex_re2.captures(&clean_article.clone()))
             .or(ex_re3.captures(&clean_article.clone())) {
         Some(captures) => captures.name("excerpt").unwrap().to_string(),
@@ -74,47 +126,66 @@ fn get_hint_from_article(article: String, word: &str, lang: &str) -> String {
         (r#"\s+"#, " "))).trim().to_string()
 }
 
-fn download_from(url: String) -> String {
+fn download_from(url: String) -> Result<String, HintError> {
     let mut client = Client::new();
-    let mut res = client.get(&url[..]).send().unwrap();
+    client.set_read_timeout(Some(Duration::from_secs(TIMEOUT_SECS)));
+    let mut res = try!(client.get(&url[..]).send());
+    match res.status {
+        StatusCode::NotFound => return Err(HintError::NotFound),
+        status if !status.is_success() => return Err(HintError::Status(status)),
+        _ => (),
+    }
     let mut body = String::new();
-    res.read_to_string(&mut body).unwrap();
-    body
+    try!(res.read_to_string(&mut body));
+    Ok(body)
+}
+
+/// Returns the URL of the raw wikitext of the article with the given title.
+fn article_url(server: &str, title: &str) -> String {
+    format!("{}/w/index.php?title={}&action=raw", server, title)
 }
 
-fn download_article(word: &String, lang: &String) -> String {
+/// Downloads the article about the word from the given Wikipedia server, following a redirection.
+pub fn download_article(server: &str, word: &str, rules: &HintRules)
+        -> Result<String, HintError> {
     let mut cased_word = String::new();
-    cased_word.extend(word[..1].chars());
-    cased_word.extend(word[1..].to_ascii_lowercase().chars());
-    let url = format!("http://{}.wikipedia.org/w/index.php?title={}&action=raw", lang, cased_word);
-    let body = download_from(url);
+    let mut chars = word.chars();
+    cased_word.extend(chars.next().into_iter().flat_map(char::to_uppercase));
+    cased_word.extend(chars.as_str().to_lowercase().chars());
+    let body = try!(download_from(article_url(server, &cased_word.replace(" ", "_"))));
     // TODO: Check whether the redirection is just because of capitalization. Otherwise ... ??
-    if let Some(captures) =
-        Regex::new(r#"^#((?i)REDIRECT|WEITERLEITUNG)\s*\[\[(?P<redir>[^\]]*)\]\]"#)
-            .unwrap().captures(&body) {
-        let url = format!("http://{}.wikipedia.org/w/index.php?title={}&action=raw",
-                          lang, captures.name("redir").unwrap().replace(" ", "_"));
-        return download_from(url);
+    let redirect_re = rules.redirect_re().map(|re| Regex::new(&re).unwrap());
+    if let Some(captures) = redirect_re.as_ref().and_then(|re| re.captures(&body)) {
+        let title = captures.name("redir").unwrap().replace(" ", "_");
+        return download_from(article_url(server, &title));
     }
-    body
+    Ok(body)
+}
+
+/// Returns the address of the Wikipedia server for the given language.
+pub fn wikipedia_server(lang: &str) -> String {
+    format!("http://{}.wikipedia.org", lang)
 }
 
-fn get_hint(word: &String, lang: &String) -> String {
-    let article = download_article(word, lang);
+/// Returns a hint for the word extracted from the given article.
+pub fn extract_hint(article: String, word: &str, rules: &HintRules)
+        -> Result<String, HintError> {
     // TODO: Remove markup. Or better: Find some external software that removes markup.
     // TODO: Escape HTML
     // TODO: Handle disambiguations.
     // TODO: Do something (like, an anagram?) if the article doesn't exist.
-    // TODO: Handle all errors without panic!
-    // TODO: Restore umlauts.
-    get_hint_from_article(article, word, lang)
+    match get_hint_from_article(article, word, rules) {
+        ref hint if hint.is_empty() => Err(HintError::NoHint),
+        hint => Ok(hint),
+    }
 }
 
-pub fn get_hints<T: Iterator<Item = String>>(words: T, lang: String) -> HashMap<String, String> {
-    words.map(|word| {
-        let hint = get_hint(&word, &lang);
-        (word, hint)
-    }).collect()
+/// Downloads the article about the word from the given Wikipedia server and returns a hint
+/// extracted from it.
+#[cfg(test)]
+pub fn get_hint(server: &str, word: &str, rules: &HintRules) -> Result<String, HintError> {
+    let article = try!(download_article(server, word, rules));
+    extract_hint(article, word, rules)
 }
 
 #[test]
@@ -134,7 +205,121 @@ fn test_get_hint_from_article() {
         r#"Umgebung zu erschaffen."#).to_string();
     let description = r#"eine Layout-Engine, welche von Mozilla und Samsung entwickelt wird"#
         .to_string();
-    assert_eq!(description, get_hint_from_article(article, "Servo", "de"));
+    assert_eq!(description, get_hint_from_article(article, "Servo", &HintRules::for_lang("de")));
     let convert = r#"distance of {{convert|2,900|km|mi}}"#.to_string();
-    assert_eq!(r#"distance of 2,900 km"#.to_string(), get_hint_from_article(convert, "Foo", "en"));
+    let en = HintRules::for_lang("en");
+    assert_eq!(r#"distance of 2,900 km"#.to_string(), get_hint_from_article(convert, "Foo", &en));
+}
+
+#[test]
+fn test_get_hint_from_article_fr() {
+    let rules = HintRules::for_lang("fr");
+    let article = concat!("{{Infobox Logiciel\n| nom = Servo\n}}\n",
+                          "'''Servo''' est un [[moteur de rendu]] développé par [[Mozilla]].\n");
+    assert_eq!("un moteur de rendu développé par Mozilla",
+               get_hint_from_article(article.to_string(), "Servo", &rules));
+    let disambiguation = concat!("'''Servo''' peut désigner :\n",
+                                 "* un [[moteur de rendu]]\n",
+                                 "* un [[robot]]\n");
+    assert_eq!("un moteur de rendu",
+               get_hint_from_article(disambiguation.to_string(), "Servo", &rules));
+}
+
+#[test]
+fn test_get_hint_from_article_es() {
+    let rules = HintRules::for_lang("es");
+    let article = concat!("{{Ficha de software\n| nombre = Servo\n}}\n",
+                          "'''Servo''' es un [[motor de renderizado]] libre.\n");
+    assert_eq!("un motor de renderizado libre",
+               get_hint_from_article(article.to_string(), "Servo", &rules));
+    let disambiguation = concat!("'''Servo''' puede referirse a:\n",
+                                 "* un [[motor de renderizado]]\n",
+                                 "* un [[robot]]\n");
+    assert_eq!("un motor de renderizado",
+               get_hint_from_article(disambiguation.to_string(), "Servo", &rules));
+}
+
+#[test]
+fn test_get_hint_from_article_it() {
+    let rules = HintRules::for_lang("it");
+    let article = concat!("{{Infobox software\n| Nome = Servo\n}}\n",
+                          "'''Servo''' è un [[motore di rendering]] sviluppato da [[Mozilla]].\n");
+    assert_eq!("un motore di rendering sviluppato da Mozilla",
+               get_hint_from_article(article.to_string(), "Servo", &rules));
+    let disambiguation = concat!("'''Servo''' può riferirsi a:\n",
+                                 "* un [[motore di rendering]]\n",
+                                 "* un [[robot]]\n");
+    assert_eq!("un motore di rendering",
+               get_hint_from_article(disambiguation.to_string(), "Servo", &rules));
+}
+
+#[test]
+fn test_get_hint_from_article_nl() {
+    let rules = HintRules::for_lang("nl");
+    let article = concat!("{{Zie dp|Servo}}\n",
+                          "'''Servo''' is een [[layout-engine]] van [[Mozilla]].\n");
+    assert_eq!("een layout-engine van Mozilla",
+               get_hint_from_article(article.to_string(), "Servo", &rules));
+    let disambiguation = concat!("'''Servo''' kan verwijzen naar:\n",
+                                 "* een [[layout-engine]]\n",
+                                 "* een [[robot]]\n");
+    assert_eq!("een layout-engine",
+               get_hint_from_article(disambiguation.to_string(), "Servo", &rules));
+}
+
+/// Starts a local HTTP server that responds to `action=raw` requests with the given status and
+/// article for each title, and with 404 for unknown titles. Returns its address.
+#[cfg(test)]
+fn serve_articles(articles: Vec<(&'static str, u16, &'static str)>) -> String {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let server = format!("http://{}", listener.local_addr().unwrap());
+    thread::spawn(move || for stream in listener.incoming() {
+        let mut stream = stream.unwrap();
+        let mut request = String::new();
+        BufReader::new(&stream).read_line(&mut request).unwrap();
+        let title = request.split(|c| c == '=' || c == '&').nth(1).unwrap_or("").to_string();
+        let (status, body) = articles.iter().find(|&&(t, _, _)| t == title)
+            .map_or((404, ""), |&(_, status, body)| (status, body));
+        write!(stream, "HTTP/1.0 {} X\r\nContent-Length: {}\r\n\r\n{}", status, body.len(), body)
+            .unwrap();
+    });
+    server
+}
+
+#[test]
+fn test_get_hint() {
+    let server = serve_articles(vec!(
+        ("Rust", 200, "#REDIRECT [[Rust (programming language)]]"),
+        ("Rust_(programming_language)", 200, "'''Rust''' is a programming language.\n"),
+        ("Moteur", 200, "#REDIRECTION [[Servo]]"),
+        ("Servo", 200, "'''Servo''' est un moteur de rendu.\n"),
+        ("Empty", 200, ""),
+        ("Broken", 500, "")));
+    let (en, fr) = (HintRules::for_lang("en"), HintRules::for_lang("fr"));
+    assert_eq!("a programming language", get_hint(&server, "RUST", &en).unwrap());
+    assert_eq!("un moteur de rendu", get_hint(&server, "SERVO", &fr).unwrap());
+    assert_eq!("Servo est un ... de rendu.", get_hint(&server, "MOTEUR", &fr).unwrap());
+    // There are no built-in rules for Esperanto, but a sentence is still found.
+    let eo = HintRules::for_lang("eo");
+    assert_eq!("... est un moteur de rendu.", get_hint(&server, "SERVO", &eo).unwrap());
+    match get_hint(&server, "MISSING", &en) {
+        Err(HintError::NotFound) => (),
+        result => panic!("unexpected result: {:?}", result),
+    }
+    match get_hint(&server, "EMPTY", &en) {
+        Err(HintError::NoHint) => (),
+        result => panic!("unexpected result: {:?}", result),
+    }
+    match get_hint(&server, "BROKEN", &en) {
+        Err(HintError::Status(status)) => assert_eq!(500, status.to_u16()),
+        result => panic!("unexpected result: {:?}", result),
+    }
+    match get_hint("http://127.0.0.1:1", "RUST", &en) {
+        Err(HintError::Http(_)) => (),
+        result => panic!("unexpected result: {:?}", result),
+    }
 }
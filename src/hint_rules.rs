@@ -0,0 +1,139 @@
+//! Language-specific rules for extracting hints from Wikipedia articles.
+//!
+//! The rules are read from a text file with one rule per line: a key, followed by `=` and a list
+//! of phrases separated by `|`. Empty lines and lines starting with `#` are ignored. E. g.:
+//!
+//! ```text
+//! # The verbs that introduce a description of the word, as in "Rust is ...".
+//! copulas = is | are | was | were
+//! # The phrases that introduce the list of meanings on a disambiguation page.
+//! disambiguations = may refer to
+//! # The keywords that introduce a redirection to another article.
+//! redirects = REDIRECT
+//! # The templates that are removed entirely, e. g. all whose names start with "Infobox".
+//! removed_templates = Infobox | Other uses
+//! ```
+//!
+//! Rules for several languages are built in, in the `rules` directory.
+use regex;
+use std::io::{BufRead, Error, ErrorKind, Result};
+
+/// The built-in rules, by language code.
+const BUILT_IN: [(&'static str, &'static str); 6] = [
+    ("de", include_str!("../rules/de.txt")),
+    ("en", include_str!("../rules/en.txt")),
+    ("es", include_str!("../rules/es.txt")),
+    ("fr", include_str!("../rules/fr.txt")),
+    ("it", include_str!("../rules/it.txt")),
+    ("nl", include_str!("../rules/nl.txt")),
+];
+
+/// The phrases used to find the relevant parts of an article in a particular language.
+#[derive(Clone, Debug, Default)]
+pub struct HintRules {
+    /// The verbs that introduce a description of the word, as in "Rust is ...".
+    pub copulas: Vec<String>,
+    /// The phrases that introduce the list of meanings on a disambiguation page.
+    pub disambiguations: Vec<String>,
+    /// The keywords that introduce a redirection to another article.
+    pub redirects: Vec<String>,
+    /// The prefixes of the names of templates that are removed entirely.
+    pub removed_templates: Vec<String>,
+}
+
+/// Returns a regular expression matching any of the phrases, or `None` if there are none.
+fn alternatives(phrases: &Vec<String>) -> Option<String> {
+    if phrases.is_empty() {
+        return None;
+    }
+    let escaped: Vec<String> = phrases.iter().map(|phrase| regex::quote(phrase)).collect();
+    Some(format!("(?:{})", escaped.join("|")))
+}
+
+impl HintRules {
+    /// Returns the built-in rules for the given language or, if there are none, rules that only
+    /// recognize the generic `REDIRECT` keyword.
+    pub fn for_lang(lang: &str) -> HintRules {
+        match BUILT_IN.iter().find(|&&(l, _)| l == lang) {
+            Some(&(_, text)) => HintRules::read(text.as_bytes()).unwrap(),
+            None => HintRules {
+                redirects: vec!("REDIRECT".to_string()),
+                ..HintRules::default()
+            },
+        }
+    }
+
+    /// Reads the rules from the given reader.
+    pub fn read<T: BufRead>(reader: T) -> Result<HintRules> {
+        let mut rules = HintRules::default();
+        for (i, line) in reader.lines().enumerate() {
+            let line = try!(line);
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |msg| Error::new(ErrorKind::InvalidData,
+                                           format!("line {}: {}", i + 1, msg));
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next().unwrap().trim();
+            let value = try!(parts.next().ok_or(invalid(format!("missing = after {}", key))));
+            let phrases = value.split('|').map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+            match key {
+                "copulas" => rules.copulas.extend(phrases),
+                "disambiguations" => rules.disambiguations.extend(phrases),
+                "redirects" => rules.redirects.extend(phrases),
+                "removed_templates" => rules.removed_templates.extend(phrases),
+                _ => return Err(invalid(format!("unknown rule {}", key))),
+            }
+        }
+        Ok(rules)
+    }
+
+    /// Returns a regular expression matching any of the copulas, surrounded by spaces.
+    pub fn copula_re(&self) -> Option<String> {
+        alternatives(&self.copulas).map(|re| format!(" {} ", re))
+    }
+
+    /// Returns a regular expression matching any of the disambiguation phrases.
+    pub fn disambiguation_re(&self) -> Option<String> {
+        alternatives(&self.disambiguations)
+    }
+
+    /// Returns a regular expression matching a redirection, where the group `redir` is the title
+    /// of the target article.
+    pub fn redirect_re(&self) -> Option<String> {
+        alternatives(&self.redirects)
+            .map(|re| format!(r#"^#(?i:{})\s*\[\[(?P<redir>[^\]]*)\]\]"#, re))
+    }
+
+    /// Returns a regular expression matching the templates that are removed entirely.
+    pub fn removed_template_re(&self) -> Option<String> {
+        alternatives(&self.removed_templates)
+            .map(|re| format!(r#"\{{\{{\s*(?i:{})[^\}}]*\}}\}}"#, re))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read() {
+        let text = concat!("# Comment\n",
+                           "copulas = est | sont\n",
+                           "\n",
+                           "disambiguations = peut désigner\n",
+                           "redirects = REDIRECTION|REDIRECT\n");
+        let rules = HintRules::read(text.as_bytes()).unwrap();
+        assert_eq!(vec!("est", "sont"), rules.copulas);
+        assert_eq!(vec!("peut désigner"), rules.disambiguations);
+        assert_eq!(vec!("REDIRECTION", "REDIRECT"), rules.redirects);
+        assert!(rules.removed_templates.is_empty());
+        assert_eq!(Some(" (?:est|sont) ".to_string()), rules.copula_re());
+        assert!(HintRules::read("copulas\n".as_bytes()).is_err());
+        assert!(HintRules::read("foo = bar\n".as_bytes()).is_err());
+        for &(lang, _) in BUILT_IN.iter() {
+            assert!(!HintRules::for_lang(lang).copulas.is_empty());
+        }
+        assert_eq!(vec!("REDIRECT"), HintRules::for_lang("xx").redirects);
+    }
+}
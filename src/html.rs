@@ -1,4 +1,5 @@
-use cw::{Crosswords, Dir, Point, PrintItem};
+use cw::{Crosswords, CVec, Dir, Point, PrintItem, Range};
+use dict::Spellings;
 use std::collections::HashMap;
 use std::io::{Result, Write};
 
@@ -31,6 +32,11 @@ const CSS: &'static str = r#"
 .dark { background-color: DarkBlue; }
 .light { background-color: LightGray; }
 .blockcol { background-color: DarkBlue; }
+.cluecell {
+    background-color: LightYellow;
+    overflow: hidden;
+}
+.clue { font: 6px sans-serif; }
 "#;
 
 fn get_border_class(border: bool) -> &'static str {
@@ -43,7 +49,7 @@ fn string_for(item: PrintItem, solution: bool) -> String {
             format!(r#"<div class="low {}"></div>"#, get_border_class(b)),
         PrintItem::VertBorder(b) =>
             format!(r#"<div class="high {}"></div>"#, get_border_class(b)),
-        PrintItem::Block => 
+        PrintItem::Block(_) =>
             format!(r#"<div class="high blockcol"></div>"#),
         PrintItem::CharHint(c, hint) =>
             format!(concat!(r#"<div class = "high">"#,
@@ -56,41 +62,84 @@ fn string_for(item: PrintItem, solution: bool) -> String {
     }
 }
 
-fn write_grid<T: Write, I: Iterator<Item = PrintItem>>(writer: &mut T, items: I, solution: bool)
-        -> Result<()> {
+/// Returns an arrow pointing from the clue cell at the given point to the word in the range.
+fn get_arrow(point: Point, range: Range) -> char {
+    match (range.dir, range.point - point == range.dir.point()) {
+        (Dir::Right, true) => '\u{2192}',
+        (Dir::Down, true) => '\u{2193}',
+        (Dir::Right, false) => '\u{21B3}',
+        (Dir::Down, false) => '\u{21B4}',
+    }
+}
+
+/// Returns the hint for the word or, if there is none, the word in its original spelling in
+/// brackets.
+fn get_hint(word: &CVec, hint_text: &HashMap<String, String>, spellings: &Spellings) -> String {
+    let word_string: String = word.iter().cloned().collect();
+    hint_text.get(&word_string).cloned().unwrap_or(format!("[{}]", spellings.display(word)))
+}
+
+/// Returns the HTML for a clue cell containing the hints for the adjacent words, with arrows
+/// pointing to them. If there are no such words, it is displayed as a block.
+fn clue_cell_string(cw: &Crosswords, point: Point, hint_text: &HashMap<String, String>,
+                    spellings: &Spellings) -> String {
+    let ranges = cw.get_clued_ranges(point);
+    if ranges.is_empty() {
+        return string_for(PrintItem::Block(point), false);
+    }
+    let clues: String = ranges.into_iter().map(|range| {
+        let hint = get_hint(&cw.chars(range).collect(), hint_text, spellings);
+        format!(r#"<div class="clue">{} {}</div>"#, get_arrow(point, range), hint)
+    }).collect();
+    format!(r#"<div class="high cluecell">{}</div>"#, clues)
+}
+
+fn write_grid<T: Write>(writer: &mut T, cw: &Crosswords, solution: bool,
+                        hint_text: &HashMap<String, String>, spellings: &Spellings,
+                        clue_cells: bool) -> Result<()> {
     try!(writeln!(writer, r#"<div class="row">"#));
-    for item in items {
-        try!(writer.write_all(&string_for(item, solution).as_bytes()))
+    for item in cw.print_items() {
+        let string = match item {
+            PrintItem::Block(p) if clue_cells => clue_cell_string(cw, p, hint_text, spellings),
+            PrintItem::CharHint(c, _) if clue_cells => {
+                string_for(PrintItem::CharHint(c, None), solution)
+            }
+            item => string_for(item, solution),
+        };
+        try!(writer.write_all(&string.as_bytes()))
     }
     try!(writeln!(writer, "</div>"));
     Ok(())
 }
 
-fn write_hints<T: Write>(writer: &mut T, cw: &Crosswords, dir: Dir,
-                         hint_text: &HashMap<String, String>) -> Result<()> {
+/// Writes the numbered list of hints in the given direction. In the solution, each hint is followed
+/// by the word in its original spelling.
+fn write_hints<T: Write>(writer: &mut T, cw: &Crosswords, dir: Dir, solution: bool,
+                         hint_text: &HashMap<String, String>, spellings: &Spellings)
+        -> Result<()> {
     try!(writeln!(writer, "<p><br><b>{}:</b>&nbsp;", match dir {
         Dir::Right => "Horizontal",
         Dir::Down => "Vertical",
     }));
-    let mut hint_count = 0;
-    for y in 0..cw.get_height() {
-        for x in 0..cw.get_width() {
-            let p = Point::new(x as i32, y as i32);
-            if cw.has_hint_at(p) { hint_count += 1; }
-            if cw.has_hint_at_dir(p, dir) {
-                let word: String = cw.chars_at(p, dir).collect();
-                let hint = hint_text.get(&word).cloned().unwrap_or(format!("[{}]", word));
-                try!(write!(writer, "<b>{}.</b> {} &nbsp;", hint_count, hint));
-            }
+    for (hint_count, range) in cw.numbered_word_ranges().into_iter().filter(|r| r.1.dir == dir) {
+        let word: CVec = cw.chars(range).collect();
+        let hint = get_hint(&word, hint_text, spellings);
+        try!(write!(writer, "<b>{}.</b> {} ", hint_count, hint));
+        if solution && hint_text.contains_key(&word.iter().cloned().collect::<String>()) {
+            try!(write!(writer, "<i>({})</i> ", spellings.display(&word)));
         }
+        try!(write!(writer, "&nbsp;"));
     }
     try!(writeln!(writer, "</p>"));
     Ok(())
 }
 
-/// Write the crosswords to the given writer as an HTML page.
+/// Write the crosswords to the given writer as an HTML page. If `clue_cells` is `true`, the hints
+/// are written into the empty cells next to the words, as in an arrowword grid, instead of below
+/// the grid. Words without a hint are shown in their original spelling.
 pub fn write_html<T: Write>(writer: &mut T, cw: &Crosswords, solution: bool,
-                            hint_text: &HashMap<String, String>) -> Result<()> {
+                            hint_text: &HashMap<String, String>, spellings: &Spellings,
+                            clue_cells: bool) -> Result<()> {
     try!(writeln!(writer, r#"<!doctype html>"#));
     try!(writeln!(writer, r#"<head>"#));
     try!(writeln!(writer, r#"<meta charset="utf-8" />"#));
@@ -98,10 +147,12 @@ pub fn write_html<T: Write>(writer: &mut T, cw: &Crosswords, solution: bool,
     try!(writeln!(writer, r#"<title>Crosswords</title>"#));
     try!(writeln!(writer, r#"</head><body>"#));
     try!(writeln!(writer, r#"<div style="width: {}px">"#, cw.get_width() * 32 + 2));
-    try!(write_grid(writer, cw.print_items(), solution));
+    try!(write_grid(writer, cw, solution, hint_text, spellings, clue_cells));
     try!(writeln!(writer, r#"</div><br><div style="clear: both"></div>"#));
-    try!(write_hints(writer, &cw, Dir::Right, hint_text));
-    try!(write_hints(writer, &cw, Dir::Down, hint_text));
+    if !clue_cells {
+        try!(write_hints(writer, &cw, Dir::Right, solution, hint_text, spellings));
+        try!(write_hints(writer, &cw, Dir::Down, solution, hint_text, spellings));
+    }
     try!(writeln!(writer, "<br></body>"));
     Ok(())
 }
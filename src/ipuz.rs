@@ -0,0 +1,216 @@
+//! Reading and writing crosswords in the [ipuz](http://ipuz.org) format.
+//!
+//! Borders between two letters are represented as bars in the cell styles: `R` for a border to
+//! the right of the cell and `B` for a border below it. Empty cells are written as blocks.
+use cw::{BLOCK, Crosswords, CVec, Dir, Point};
+use rustc_serialize::json::{Json, ToJson};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Error, ErrorKind, Read, Result, Write};
+
+const VERSION: &'static str = "http://ipuz.org/v2";
+const KIND: &'static str = "http://ipuz.org/crossword#1";
+const IPUZ_BLOCK: &'static str = "#";
+const TITLE: &'static str = "Crosswords";
+
+fn invalid_data(msg: String) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+fn dir_name(dir: Dir) -> &'static str {
+    match dir {
+        Dir::Right => "Across",
+        Dir::Down => "Down",
+    }
+}
+
+/// Returns the bars of the cell at the given point, as an ipuz style string.
+fn get_bars(cw: &Crosswords, point: Point) -> String {
+    let mut bars = String::new();
+    for &(dir, bar) in &[(Dir::Right, 'R'), (Dir::Down, 'B')] {
+        if cw.is_letter(point + dir.point()) && cw.get_border(point, dir) {
+            bars.push(bar);
+        }
+    }
+    bars
+}
+
+fn puzzle_cell(number: u32, bars: String) -> Json {
+    if bars.is_empty() {
+        return number.to_json();
+    }
+    let mut style = BTreeMap::new();
+    style.insert("barred".to_string(), bars.to_json());
+    let mut cell = BTreeMap::new();
+    cell.insert("cell".to_string(), number.to_json());
+    cell.insert("style".to_string(), Json::Object(style));
+    Json::Object(cell)
+}
+
+/// Write the crosswords and the given hints to the given writer in the ipuz format. Words without
+/// a hint get their solution in brackets as the clue.
+pub fn write_ipuz<T: Write>(writer: &mut T, cw: &Crosswords, hint_text: &HashMap<String, String>)
+        -> Result<()> {
+    let ranges = cw.numbered_word_ranges();
+    let numbers: HashMap<Point, u32> = ranges.iter().map(|&(n, range)| (range.point, n)).collect();
+    let mut puzzle = Vec::new();
+    let mut solution = Vec::new();
+    for y in 0..(cw.get_height() as i32) {
+        let mut puzzle_row = Vec::new();
+        let mut solution_row = Vec::new();
+        for x in 0..(cw.get_width() as i32) {
+            let p = Point::new(x, y);
+            if cw.is_letter(p) {
+                let number = numbers.get(&p).cloned().unwrap_or(0);
+                puzzle_row.push(puzzle_cell(number, get_bars(cw, p)));
+                solution_row.push(cw.get_char(p).unwrap().to_string().to_json());
+            } else {
+                puzzle_row.push(IPUZ_BLOCK.to_json());
+                solution_row.push(IPUZ_BLOCK.to_json());
+            }
+        }
+        puzzle.push(Json::Array(puzzle_row));
+        solution.push(Json::Array(solution_row));
+    }
+    let mut clues = BTreeMap::new();
+    for &dir in &[Dir::Right, Dir::Down] {
+        let dir_clues = ranges.iter().filter(|&&(_, range)| range.dir == dir).map(|&(n, range)| {
+            let word: String = cw.chars(range).collect();
+            let hint = hint_text.get(&word).cloned().unwrap_or(format!("[{}]", word));
+            Json::Array(vec!(n.to_json(), hint.to_json()))
+        }).collect();
+        clues.insert(dir_name(dir).to_string(), Json::Array(dir_clues));
+    }
+    let mut dimensions = BTreeMap::new();
+    dimensions.insert("width".to_string(), cw.get_width().to_json());
+    dimensions.insert("height".to_string(), cw.get_height().to_json());
+    let mut root = BTreeMap::new();
+    root.insert("version".to_string(), VERSION.to_json());
+    root.insert("kind".to_string(), Json::Array(vec!(KIND.to_json())));
+    root.insert("title".to_string(), TITLE.to_json());
+    root.insert("dimensions".to_string(), Json::Object(dimensions));
+    root.insert("block".to_string(), IPUZ_BLOCK.to_json());
+    root.insert("puzzle".to_string(), Json::Array(puzzle));
+    root.insert("solution".to_string(), Json::Array(solution));
+    root.insert("clues".to_string(), Json::Object(clues));
+    writeln!(writer, "{}", Json::Object(root).pretty())
+}
+
+/// Returns the cell at the given position of a two-dimensional array.
+fn get_cell<'a>(json: &'a Json, key: &str, x: usize, y: usize) -> Result<&'a Json> {
+    json.find(key).and_then(Json::as_array)
+        .and_then(|rows| rows.get(y)).and_then(Json::as_array)
+        .and_then(|row| row.get(x))
+        .ok_or(invalid_data(format!("missing cell ({}, {}) in {}", x, y, key)))
+}
+
+/// Returns the bars of the given puzzle cell.
+fn get_cell_bars(cell: &Json) -> &str {
+    cell.find("style").and_then(|style| style.find("barred")).and_then(Json::as_string)
+        .unwrap_or("")
+}
+
+/// Returns the letter in the given solution cell, or `BLOCK` if it is a block or omitted.
+fn get_cell_char(cell: &Json, block: &str) -> char {
+    match cell.find("value").unwrap_or(cell).as_string() {
+        Some(s) if s != block => {
+            s.chars().next().map_or(BLOCK, |c| c.to_uppercase().next().unwrap())
+        }
+        _ => BLOCK,
+    }
+}
+
+/// Returns the number and text of the given clue.
+fn get_clue(clue: &Json) -> Option<(u64, String)> {
+    match *clue {
+        Json::Array(ref array) => array.get(0).and_then(Json::as_u64)
+            .and_then(|n| array.get(1).and_then(Json::as_string).map(|s| (n, s.to_string()))),
+        Json::Object(_) => clue.find("number").and_then(Json::as_u64)
+            .and_then(|n| clue.find("clue").and_then(Json::as_string).map(|s| (n, s.to_string()))),
+        _ => None,
+    }
+}
+
+/// Read a crosswords grid and its hints from the given reader in the ipuz format. The hints are
+/// returned as a map from each word to its clue.
+pub fn read_ipuz<T: Read>(reader: &mut T) -> Result<(Crosswords, HashMap<String, String>)> {
+    let json = try!(Json::from_reader(reader).map_err(|err| invalid_data(err.to_string())));
+    let get_dimension = |key| json.find("dimensions").and_then(|d| d.find(key))
+        .and_then(Json::as_u64).map(|n| n as usize).unwrap_or(0);
+    let (width, height) = (get_dimension("width"), get_dimension("height"));
+    if width < 2 || height < 2 {
+        return Err(invalid_data("invalid grid size".to_string()));
+    }
+    let block = json.find("block").and_then(Json::as_string).unwrap_or(IPUZ_BLOCK);
+    let mut chars = CVec::new();
+    let mut bars = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            let puzzle_cell = try!(get_cell(&json, "puzzle", x, y));
+            chars.push(match puzzle_cell.as_string() {
+                Some(s) if s == block => BLOCK,
+                _ if puzzle_cell.is_null() => BLOCK,
+                _ => get_cell_char(try!(get_cell(&json, "solution", x, y)), block),
+            });
+            bars.push(get_cell_bars(puzzle_cell));
+        }
+    }
+    let has_bar = |x: usize, y: usize, bar: char| bars[x + width * y].contains(bar);
+    let right_border = (0..height).flat_map(|y| (0..(width - 1)).map(move |x| (x, y)))
+        .map(|(x, y)| has_bar(x, y, 'R') || has_bar(x + 1, y, 'L')).collect();
+    let down_border = (0..(height - 1)).flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| has_bar(x, y, 'B') || has_bar(x, y + 1, 'T')).collect();
+    let cw = try!(Crosswords::from_cells(width, height, chars, right_border, down_border)
+                  .map_err(invalid_data));
+
+    let ranges = cw.numbered_word_ranges();
+    let mut hint_text = HashMap::new();
+    for &dir in &[Dir::Right, Dir::Down] {
+        let clues = json.find("clues").and_then(|clues| clues.as_object()).into_iter()
+            .flat_map(|clues| clues.iter())
+            .filter(|&(key, _)| key.split(':').next() == Some(dir_name(dir)))
+            .filter_map(|(_, clues)| clues.as_array()).flat_map(|clues| clues.iter())
+            .filter_map(get_clue);
+        for (n, clue) in clues {
+            let range = match ranges.iter().find(|&&(m, r)| m as u64 == n && r.dir == dir) {
+                Some(&(_, range)) => range,
+                None => return Err(invalid_data(format!("no word for clue {} {}",
+                                                        n, dir_name(dir)))),
+            };
+            let word: String = cw.chars(range).collect();
+            if clue != format!("[{}]", word) {
+                hint_text.insert(word, clue);
+            }
+        }
+    }
+    Ok((cw, hint_text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cw::{Crosswords, Dir, Point};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_round_trip() {
+        // Create the following grid, where the letters in the middle row don't form a word:
+        // FOO
+        // ABC
+        // BAD
+        let mut cw = Crosswords::new(3, 3);
+        cw.try_word(Point::new(0, 0), Dir::Right, &"FOO".chars().collect());
+        cw.try_word(Point::new(0, 0), Dir::Down, &"FAB".chars().collect());
+        cw.try_word(Point::new(1, 0), Dir::Down, &"OB".chars().collect());
+        cw.try_word(Point::new(2, 0), Dir::Down, &"OCD".chars().collect());
+        cw.try_word(Point::new(0, 2), Dir::Right, &"BAD".chars().collect());
+        let mut hint_text = HashMap::new();
+        hint_text.insert("FOO".to_string(), "Placeholder \"name\"".to_string());
+        hint_text.insert("OCD".to_string(), "Disorder".to_string());
+        let mut bytes = Vec::new();
+        write_ipuz(&mut bytes, &cw, &hint_text).unwrap();
+        let (read_cw, read_hint_text) = read_ipuz(&mut &bytes[..]).unwrap();
+        assert_eq!(cw.to_string(), read_cw.to_string());
+        assert_eq!(cw.get_words(), read_cw.get_words());
+        assert_eq!(hint_text, read_hint_text);
+    }
+}
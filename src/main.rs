@@ -1,38 +1,254 @@
+extern crate ctrlc;
 extern crate getopts;
 extern crate hyper;
+extern crate num_cpus;
 extern crate regex;
 extern crate rand;
+extern crate rustc_serialize;
 
 mod author;
+mod compiled;
 mod cw;
 mod dict;
 mod word_constraint;
 mod word_stats;
 
-use getopts::Options;
+use getopts::{Matches, Options};
+use rand::{Rng, SeedableRng, StdRng};
 use std::collections::HashMap;
 use std::env;
 use std::i32;
+use std::cmp;
 
+mod clues;
+mod dump;
 mod html;
 mod get_hints;
+mod hint_rules;
+mod ipuz;
+mod puz;
+mod recent;
+mod template;
 
-use author::Author;
-use cw::Crosswords;
-use dict::Dict;
-use get_hints::get_hints;
+use author::{Author, DEFAULT_MAX_NOGOODS, SearchStats, SearchStatus, Symmetry};
+use cw::{Crosswords, CVec};
+use dict::{Dict, Normalization, Spellings};
+use hint_rules::HintRules;
+use clues::{ClueChain, ClueCommand, ClueProvider, HintCache, Wikipedia};
 use std::collections::HashSet;
 use std::fs::File;
-use std::io::{BufRead, BufReader, BufWriter, Result};
+use std::io::{stderr, BufRead, BufReader, BufWriter, Read, Result, Write};
 use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
 use std::usize;
+use rustc_serialize::json::ToJson;
+use word_stats::WordStats;
 
 /// Write the crosswords grid to the file with the given name.
 fn write_html_to_file<P: AsRef<Path>>(filename: P, cw: &Crosswords, solution: bool,
+                                      hint_text: &HashMap<String, String>,
+                                      spellings: &Spellings, clue_cells: bool) -> Result<()> {
+    let file = try!(File::create(filename));
+    let mut writer = BufWriter::new(file);
+    html::write_html(&mut writer, cw, solution, hint_text, spellings, clue_cells)
+}
+
+/// Write the crosswords grid to the file with the given name in the Across Lite format. If the
+/// grid cannot be represented in that format, the file is not created.
+fn write_puz_to_file<P: AsRef<Path>>(filename: P, cw: &Crosswords,
+                                     hint_text: &HashMap<String, String>) -> Result<()> {
+    let mut bytes = Vec::new();
+    try!(puz::write_puz(&mut bytes, cw, hint_text));
+    let mut file = try!(File::create(filename));
+    file.write_all(&bytes)
+}
+
+/// Write the crosswords grid to the file with the given name in the ipuz format.
+fn write_ipuz_to_file<P: AsRef<Path>>(filename: P, cw: &Crosswords,
                                       hint_text: &HashMap<String, String>) -> Result<()> {
     let file = try!(File::create(filename));
     let mut writer = BufWriter::new(file);
-    html::write_html(&mut writer, cw, solution, hint_text)
+    ipuz::write_ipuz(&mut writer, cw, hint_text)
+}
+
+/// Read a crosswords grid and its hints from the given Across Lite or, if the file name ends in
+/// `.ipuz`, ipuz file.
+fn read_from_file(filename: &str) -> Result<(Crosswords, HashMap<String, String>)> {
+    let mut file = try!(File::open(filename));
+    if filename.ends_with(".ipuz") {
+        ipuz::read_ipuz(&mut file)
+    } else {
+        puz::read_puz(&mut file)
+    }
+}
+
+/// Write the puzzle and solution HTML files and all other requested output files.
+fn write_files(matches: &Matches, cw: &Crosswords, hint_text: &HashMap<String, String>,
+               spellings: &Spellings) {
+    let clue_cells = matches.opt_present("arrowword");
+    write_html_to_file("puzzle.html", cw, false, hint_text, spellings, clue_cells).unwrap();
+    write_html_to_file("solution.html", cw, true, hint_text, spellings, clue_cells).unwrap();
+    if let Some(filename) = matches.opt_str("puz") {
+        if let Err(err) = write_puz_to_file(&filename, cw, hint_text) {
+            println!("Failed to write {}: {}", filename, err);
+        }
+    }
+    if let Some(filename) = matches.opt_str("ipuz") {
+        write_ipuz_to_file(filename, cw, hint_text).unwrap();
+    }
+}
+
+/// Returns the normalization that applies to the option at the given position on the command line:
+/// the last one given before it or, if there is none, the first one given after it. The default
+/// is transliteration.
+fn normalization_at(matches: &Matches, pos: usize) -> Normalization {
+    let names = matches.opt_strs_pos("normalize");
+    let name = names.iter().filter(|&&(p, _)| p < pos).last().or(names.first());
+    match name {
+        None => Normalization::Transliterate,
+        Some(&(_, ref name)) => match Normalization::from_name(name) {
+            Some(normalization) => normalization,
+            None => panic!("Unknown normalization: {}", name),
+        },
+    }
+}
+
+/// Returns whether phrases of several words are accepted in the file given at the given position
+/// on the command line, according to the last `phrases` or `no_phrases` flag before it.
+fn phrases_at(matches: &Matches, pos: usize) -> bool {
+    let last_before = |name| matches.opt_positions(name).into_iter().filter(|&p| p < pos).max();
+    match (last_before("phrases"), last_before("no_phrases")) {
+        (Some(p), Some(q)) => p > q,
+        (Some(_), None) => true,
+        (None, _) => false,
+    }
+}
+
+/// Read a clue database from the given TSV or, if the file name ends in `.csv`, CSV file.
+fn read_clue_db(filename: &str, difficulty: Option<u32>, normalization: Normalization)
+        -> Result<clues::ClueDb> {
+    let file = BufReader::new(try!(File::open(filename)));
+    let mut db = clues::ClueDb::new().with_normalization(normalization);
+    if let Some(difficulty) = difficulty {
+        db = db.with_difficulty(difficulty);
+    }
+    try!(db.read(file, if filename.ends_with(".csv") { ',' } else { '\t' }));
+    Ok(db)
+}
+
+/// Returns the language of the hints given on the command line.
+fn hint_lang(matches: &Matches) -> String {
+    matches.opt_str("lang").or(matches.opt_str("wikipedia")).unwrap_or("en".to_string())
+}
+
+/// Read the hint extraction rules from the file given on the command line, or return the
+/// built-in ones for the language.
+fn read_hint_rules(matches: &Matches) -> Result<HintRules> {
+    match matches.opt_str("hint_rules") {
+        None => Ok(HintRules::for_lang(&hint_lang(matches))),
+        Some(filename) => HintRules::read(BufReader::new(try!(File::open(filename)))),
+    }
+}
+
+/// Create the Wikipedia clue provider with the server, cache and rules given on the command line.
+fn create_wikipedia(matches: &Matches) -> Result<Wikipedia> {
+    let mut wikipedia = Wikipedia::new().with_offline(matches.opt_present("offline"));
+    if let Some(server) = matches.opt_str("wikipedia_server") {
+        wikipedia = wikipedia.with_server(server);
+    }
+    if let Some(dir) = matches.opt_str("hint_cache") {
+        let mut cache = HintCache::new(dir);
+        if let Some(days) = matches.opt_str("hint_cache_days") {
+            let days: u64 = days.parse().unwrap();
+            cache = cache.with_max_age(Duration::from_secs(days * 24 * 60 * 60));
+        }
+        wikipedia = wikipedia.with_cache(cache);
+    }
+    if matches.opt_present("hint_rules") {
+        wikipedia = wikipedia.with_rules(&hint_lang(matches), try!(read_hint_rules(matches)));
+    }
+    Ok(wikipedia)
+}
+
+/// Create the chain of clue providers, in the order in which they are given on the command line.
+fn create_clue_chain(matches: &Matches) -> Result<ClueChain> {
+    let difficulty = matches.opt_str("difficulty").map(|s| s.parse().unwrap());
+    let mut sources = Vec::new();
+    for name in &["clues", "clue_command", "wikipedia"] {
+        sources.extend(matches.opt_strs_pos(name).into_iter().map(|(pos, s)| (pos, *name, s)));
+    }
+    sources.sort();
+    let mut chain = ClueChain::new();
+    for (pos, name, value) in sources {
+        let provider: Box<ClueProvider> = match name {
+            "clues" => {
+                let normalization = normalization_at(matches, pos);
+                Box::new(try!(read_clue_db(&value, difficulty, normalization)))
+            }
+            "clue_command" => Box::new(try!(ClueCommand::new(&value))),
+            _ => Box::new(try!(create_wikipedia(matches))),
+        };
+        chain = chain.with_provider(provider);
+    }
+    Ok(chain)
+}
+
+/// Read the given Wikipedia dump and write the hints for all words in the dictionaries to the
+/// given clue file.
+fn index_dump(matches: &Matches, dump_filename: &str,
+              dict_filenames: &Vec<(String, Normalization, bool)>, output: &str)
+        -> Result<usize> {
+    let mut spellings = Spellings::new();
+    let mut words = HashSet::new();
+    for &(ref filename, normalization, phrases) in dict_filenames {
+        let file = BufReader::new(try!(File::open(filename)));
+        words.extend(file.lines().filter_map(Result::ok).filter_map(|line| {
+            spellings.add(dict::split_score(&line).0.to_string(), normalization, phrases)
+        }));
+    }
+    let mut writer = BufWriter::new(try!(File::create(output)));
+    let rules = try!(read_hint_rules(matches));
+    let normalization = normalization_at(matches, usize::MAX);
+    dump::index_dump(|| dump::open_dump(dump_filename), &words, normalization,
+                     &hint_lang(matches), &rules, &mut writer)
+}
+
+/// Append the word lengths to the hints for phrases, e. g. `A frozen dessert (3,5)` for
+/// `ICECREAM`.
+fn add_enumerations(hint_text: &mut HashMap<String, String>, cw: &Crosswords,
+                    spellings: &Spellings) {
+    for cvec in cw.get_words() {
+        let word: String = cvec.iter().cloned().collect();
+        if let (Some(hint), Some(enumeration)) = (hint_text.get_mut(&word),
+                                                  spellings.enumeration(cvec)) {
+            hint.push(' ');
+            hint.push_str(enumeration);
+        }
+    }
+}
+
+/// Read the words that must never appear in the crosswords: the ones in the exclude files, which
+/// may also be phrases, and the ones used in the recent puzzles.
+fn get_excluded_words(matches: &Matches) -> Result<HashSet<CVec>> {
+    let mut excluded = HashSet::new();
+    for (pos, filename) in matches.opt_strs_pos("exclude") {
+        let normalization = normalization_at(matches, pos);
+        let file = BufReader::new(try!(File::open(filename)));
+        for line in file.lines() {
+            if let Some((word, _)) = normalization.normalize_phrase(try!(line)) {
+                excluded.insert(word);
+            }
+        }
+    }
+    if let Some(filename) = matches.opt_str("recent") {
+        let count = matches.opt_str("recent_count").map_or(10, |s| s.parse().unwrap());
+        excluded.extend(try!(recent::read_recent_words(filename, count)));
+    }
+    Ok(excluded)
 }
 
 /// Print the usage help message.
@@ -41,26 +257,125 @@ fn print_usage(program: &str, opts: Options) {
     print!("{}", opts.usage(&brief));
 }
 
-/// Score the crosswords grid according to how many borders and favorite words it contains.
-fn evaluate(cw: &Crosswords, author: &Author) -> i32 {
+/// Returns the index of the dictionary containing the given word, or None if not found.
+fn get_word_category(dicts: &Vec<Dict>, word: &CVec) -> Option<usize> {
+    dicts.iter().position(|dict| dict.contains(word))
+}
+
+/// Returns the average and the minimum score of the words in the grid, or `None` if none of them
+/// is in any dictionary.
+fn get_word_scores(cw: &Crosswords, dicts: &Vec<Dict>) -> Option<(u32, u32)> {
+    // Pinned words from the template don't need to be in any dictionary.
+    let scores: Vec<u32> = cw.get_words().iter()
+        .filter_map(|word| dicts.iter().filter_map(|dict| dict.score(word)).next())
+        .collect();
+    let min = match scores.iter().min() {
+        Some(&min) => min,
+        None => return None,
+    };
+    Some((scores.iter().sum::<u32>() / scores.len() as u32, min))
+}
+
+/// Score the crosswords grid according to how many borders and favorite words it contains, and
+/// the average and minimum scores of its words.
+fn evaluate(cw: &Crosswords, dicts: &Vec<Dict>) -> i32 {
     let empty_borders = (cw.max_border_count() - cw.count_borders()) as i32;
     let mut word_count = 0;
     let mut word_category_count = 0;
     for word in cw.get_words() {
         word_count += 1;
-        word_category_count += author.get_word_category(word).unwrap() as i32;
+        // Pinned words from the template don't need to be in any dictionary.
+        word_category_count += get_word_category(dicts, word).unwrap_or(0) as i32;
     }
-    empty_borders + word_count - 2 * word_category_count
+    let score_value = get_word_scores(cw, dicts).map_or(0, |(avg, min)| (avg + min) as i32 / 10);
+    empty_borders + word_count - 2 * word_category_count + score_value
 }
 
-/// Print the crosswords grid and the word count.
-fn print_cw(cw: &Crosswords, author: &Author) {
+/// Print the crosswords grid, the word count and the word scores.
+fn print_cw(cw: &Crosswords, dicts: &Vec<Dict>) {
     println!("{} / {} words are favorites. Score: {}",
-        cw.get_words().iter().filter(|w| author.get_word_category(&w) == Some(0)).count(),
-        cw.get_words().len(), evaluate(&cw, author));
+        cw.get_words().iter().filter(|w| get_word_category(dicts, &w) == Some(0)).count(),
+        cw.get_words().len(), evaluate(&cw, dicts));
+    if let Some((avg, min)) = get_word_scores(cw, dicts) {
+        println!("Word scores: {} on average, {} minimum", avg, min);
+    }
     println!("{}", cw);
 }
 
+/// The settings for creating an `Author`, which are shared by all search threads.
+struct AuthorConfig {
+    template: template::Template,
+    required_words: HashSet<CVec>,
+    clue_cells: bool,
+    symmetry: Option<Symmetry>,
+    min_crossing: usize,
+    min_crossing_rel: f32,
+    max_attempts: usize,
+    max_nogoods: usize,
+    verbose: bool,
+    profiling: bool,
+    deadline: Option<Instant>,
+    cancel_flag: Arc<AtomicBool>,
+}
+
+impl AuthorConfig {
+    /// Create an `Author` with these settings and the given seed.
+    fn create_author<'a>(&self, dicts: &'a Vec<Dict>, stats: Arc<WordStats>, seed: usize)
+            -> Author<'a> {
+        let mut author = Author::new(&self.template.cw, dicts, stats)
+            .with_bars(self.template.bars.iter().cloned())
+            .with_required_words(self.required_words.iter())
+            .with_clue_cells(self.clue_cells)
+            .with_min_crossing(self.min_crossing, self.min_crossing_rel)
+            .with_verbosity(self.verbose)
+            .with_profiling(self.profiling)
+            .with_max_attempts(self.max_attempts)
+            .with_max_nogoods(self.max_nogoods)
+            .with_seed(seed)
+            .with_cancel_flag(self.cancel_flag.clone());
+        if let Some(symmetry) = self.symmetry {
+            author = author.with_block_style(symmetry);
+        }
+        if let Some(deadline) = self.deadline {
+            author = author.with_deadline(deadline);
+        }
+        author
+    }
+}
+
+/// Start the given number of threads that search for a grid for each of the samples, where the
+/// `i`-th sample uses the seed `seed + i`, and return a receiver for the sample indices, the
+/// search results and statistics. The threads stop once all samples are done or the search was
+/// interrupted.
+fn spawn_searches(config: Arc<AuthorConfig>, dicts: Arc<Vec<Dict>>, samples: usize,
+                  threads: usize, seed: usize)
+        -> Receiver<(usize, SearchStatus, Crosswords, SearchStats)> {
+    let stats = Author::create_stats(&dicts);
+    let next_sample = Arc::new(AtomicUsize::new(0));
+    let (sender, receiver) = channel();
+    for _ in 0..cmp::min(threads, samples) {
+        let config = config.clone();
+        let dicts = dicts.clone();
+        let stats = stats.clone();
+        let next_sample = next_sample.clone();
+        let sender = sender.clone();
+        thread::spawn(move || loop {
+            let i = next_sample.fetch_add(1, Ordering::SeqCst);
+            if i >= samples {
+                break;
+            }
+            let mut author = config.create_author(&dicts, stats.clone(), seed.wrapping_add(i));
+            let (status, cw) = author.complete_cw();
+            let interrupted = status == SearchStatus::TimeUp || status == SearchStatus::Cancelled;
+            let search_stats = author.get_search_stats().clone();
+            if sender.send((i, status, cw, search_stats)).is_err() || interrupted {
+                break;
+            }
+        });
+    }
+    receiver
+}
+
 /// Create the Options object containing the list of valid command line options.
 fn create_opts() -> Options {
     let mut opts = Options::new();
@@ -69,30 +384,151 @@ fn create_opts() -> Options {
     opts.optopt("p", "min_crossing_percent",
                 "minimum percentage letters of any given word shared with another word", "FLOAT");
     opts.optmulti("d", "dict", "a dictionary file", "FILENAME");
+    opts.optmulti("", "normalize", "how to convert the letters of the words in the following \
+                                    dictionary and clue files; defaults to transliterate",
+                  "keep|transliterate|fold");
+    opts.optflagmulti("", "phrases", "accept phrases of several words, like \"ice cream\", in the \
+                                     following dictionary files");
+    opts.optflagmulti("", "no_phrases", "only accept single words in the following dictionary \
+                                        files; this is the default");
+    opts.optmulti("", "exclude", "a file with words that must never appear in the crosswords, \
+                                 regardless of the dictionary", "FILENAME");
+    opts.optopt("", "recent", "a log of the words used in previous crosswords; the new crosswords \
+                               is added to it, and the words from the last recent_count ones are \
+                               excluded", "FILENAME");
+    opts.optopt("", "recent_count", "the number of previous crosswords whose words are excluded; \
+                                     defaults to 10", "INTEGER");
+    opts.optopt("", "require", "a file with words that must appear in the crosswords", "FILENAME");
     opts.optflag("h", "help", "print this help menu");
     opts.optflag("v", "verbose", "print the current grid status during computation");
+    opts.optopt("", "stats", "print statistics about the search to stderr at the end, as text or \
+                              json", "FORMAT");
+    opts.optflag("", "check_estimates", "compare the estimated numbers of matching words with the \
+                                         actual ones in the statistics; slows down the search");
     opts.optopt("m", "min_word_len", "don't use words shorter than that", "INTEGER");
+    opts.optopt("", "min_score", "don't use words with a lower score than that; words without a \
+                                  score in the dictionary have a score of 50", "INTEGER");
     opts.optopt("", "samples", "number of grids to create and select the best from", "INTEGER");
+    opts.optopt("", "threads", "number of grids to create in parallel; defaults to the number of \
+                                CPUs", "INTEGER");
     opts.optopt("", "wikipedia", "use hints from Wikipedia in the given language", "LANGUAGE");
+    opts.optopt("", "wikipedia_server", "download the articles from the given server instead, \
+                                         e. g. a local mirror", "URL");
+    opts.optopt("", "hint_cache", "store the downloaded articles and hints in the given directory \
+                                   and reuse them in later runs", "DIRECTORY");
+    opts.optopt("", "hint_cache_days", "download the articles again if the cached ones are older \
+                                        than the given number of days", "INTEGER");
+    opts.optflag("", "offline", "don't download any articles; only use the cached ones");
+    opts.optopt("", "hint_rules", "read the rules for extracting hints from articles in the hint \
+                                   language from the given file", "FILENAME");
+    opts.optopt("", "index_dump", "instead of creating a crosswords, write the hints for all \
+                                   dictionary words from the given Wikipedia XML dump to the \
+                                   index_output file", "FILENAME");
+    opts.optopt("", "compile", "instead of creating a crosswords, write the dictionaries to the \
+                                given file, which can be loaded quickly with the compiled option",
+                "FILENAME");
+    opts.optopt("", "compiled", "load the dictionaries from the given file written with the \
+                                 compile option; if the dictionary files or settings have \
+                                 changed since, they are read instead", "FILENAME");
+    opts.optopt("", "index_output", "the clue file to write the hints from the dump to; defaults \
+                                     to clues.tsv", "FILENAME");
+    opts.optmulti("", "clues", "use hints from the given TSV or CSV file; the hint sources are \
+                                asked in the order they are given in", "FILENAME");
+    opts.optmulti("", "clue_command", "use hints from the given program, which reads a language \
+                                       and a word per line and answers with a hint per line",
+                  "COMMAND");
+    opts.optopt("", "lang", "the language of the hints; defaults to the Wikipedia language or en",
+                "LANGUAGE");
+    opts.optopt("", "difficulty", "prefer hints from the clues file with the given difficulty",
+                "INTEGER");
     opts.optopt("", "max_attempts", "the maximum number of words to try out in each position",
                 "INTEGER");
+    opts.optopt("", "max_nogoods", "the maximum number of letter combinations that made the \
+                                    search fail to remember at the same time; defaults to 100000",
+                "INTEGER");
+    opts.optopt("t", "time_limit", "stop searching after the given number of seconds", "SECONDS");
+    opts.optopt("", "puz", "also write the crosswords to the given Across Lite file", "FILENAME");
+    opts.optopt("", "template", "start with the grid given in the template file; the size \
+                                 option is ignored", "FILENAME");
+    opts.optopt("", "ipuz", "also write the crosswords to the given ipuz file", "FILENAME");
+    opts.optopt("", "load", "load the crosswords from the given Across Lite or ipuz file instead \
+                             of creating a new one", "FILENAME");
+    opts.optopt("", "blocks", "create a block-style grid whose black squares have the given \
+                               symmetry", "rotational|mirror");
+    opts.optflag("", "arrowword", "create an arrowword grid, with the hints in cells next to the \
+                                   words");
+    opts.optopt("", "seed", "seed for the random number generator, to reproduce a previous result",
+                "INTEGER");
     opts
 }
 
-/// Return a list of dictionaries read from the given filenames.
-fn get_dicts<T: Iterator<Item = String>>(filenames: T, min_word_len: usize) -> Vec<Dict> {
+/// Return a list of dictionaries read from the given filenames, with the words normalized as
+/// specified for each file and phrases accepted if the flag is set, and record the original
+/// spellings of the words. Lines of the form `word;score` give the word a score; words with a
+/// lower score than `min_score` are left out. The words are ordered by score and shuffled using a
+/// random number generator with the given seed.
+fn get_dicts<T: Iterator<Item = (String, Normalization, bool)>>(filenames: T,
+                                                                min_word_len: usize,
+                                                                min_score: u32, seed: usize,
+                                                                spellings: &mut Spellings)
+        -> Vec<Dict> {
     let mut existing_words = HashSet::new();
-    filenames.map(|filename| {
+    let mut rng: StdRng = SeedableRng::from_seed(&[seed][..]);
+    filenames.map(|(filename, normalization, phrases)| {
         let get_file_lines = |filename| BufReader::new(filename).lines().filter_map(Result::ok);
         let file_lines = File::open(filename).map(get_file_lines).unwrap();
-        let dict = Dict::new(Dict::to_cvec_set(file_lines)
-                .difference(&existing_words)
-                .filter(|word| word.len() >= min_word_len));
+        let mut words: HashMap<CVec, u32> = HashMap::new();
+        for line in file_lines {
+            let (word, score) = dict::split_score(&line);
+            if let Some(word) = spellings.add(word.to_string(), normalization, phrases) {
+                let score = score.unwrap_or(dict::DEFAULT_SCORE);
+                let entry = words.entry(word).or_insert(score);
+                *entry = cmp::max(*entry, score);
+            }
+        }
+        let dict = Dict::with_scores(words.iter()
+                .filter(|&(word, _)| !existing_words.contains(word))
+                .filter(|&(word, &score)| word.len() >= min_word_len && score >= min_score)
+                .map(|(word, &score)| (word, score)), &mut rng);
         existing_words.extend(dict.all_words().cloned());
         dict
     }).collect()
 }
 
+/// Read the dictionaries and write them to the given compiled file, together with a fingerprint of
+/// the word lists and settings.
+fn compile_dicts(filename: &str, dict_filenames: &Vec<(String, Normalization, bool)>,
+                 min_word_len: usize, min_score: u32) -> Result<()> {
+    let fingerprint = try!(compiled::fingerprint(dict_filenames, min_word_len, min_score));
+    let mut spellings = Spellings::new();
+    // The order of the words doesn't matter: They are shuffled with the seed when loading.
+    let dicts = get_dicts(dict_filenames.iter().cloned(), min_word_len, min_score, 0,
+                          &mut spellings);
+    let mut writer = BufWriter::new(try!(File::create(filename)));
+    compiled::write_compiled(&mut writer, fingerprint, &dicts, &spellings)
+}
+
+/// Load the dictionaries from the given compiled file and shuffle the words with the given seed,
+/// in the same way as `get_dicts`. Returns `None` if the file was compiled from other word lists
+/// or with other settings.
+fn load_compiled(filename: &str, dict_filenames: &Vec<(String, Normalization, bool)>,
+                 min_word_len: usize, min_score: u32, seed: usize)
+        -> Result<Option<(Vec<Dict>, Spellings)>> {
+    let fingerprint = try!(compiled::fingerprint(dict_filenames, min_word_len, min_score));
+    let mut bytes = Vec::new();
+    try!(try!(File::open(filename)).read_to_end(&mut bytes));
+    let (mut dicts, spellings) = match try!(compiled::read_compiled(&mut &bytes[..],
+                                                                    fingerprint)) {
+        Some(result) => result,
+        None => return Ok(None),
+    };
+    let mut rng: StdRng = SeedableRng::from_seed(&[seed][..]);
+    for dict in &mut dicts {
+        dict.shuffle(&mut rng);
+    }
+    Ok(Some((dicts, spellings)))
+}
+
 pub fn main() {
     let args: Vec<String> = env::args().collect();
     let program = args[0].clone();
@@ -102,6 +538,12 @@ pub fn main() {
         print_usage(&program, opts);
         return;
     }
+    if let Some(filename) = matches.opt_str("load") {
+        let (cw, hint_text) = read_from_file(&filename).unwrap();
+        println!("{}", cw);
+        write_files(&matches, &cw, &hint_text, &Spellings::new());
+        return;
+    }
     // TODO: Sanity checks for option values; proper error messages.
     let size: Vec<usize> = matches.opt_str("s").map_or(vec!(15, 10), |s| s.split('x')
         .map(|s| s.parse().unwrap()).collect());
@@ -109,45 +551,202 @@ pub fn main() {
     let min_crossing = matches.opt_str("c").map_or(2, |s| s.parse().unwrap());
     let min_crossing_rel = 0.01 * matches.opt_str("p").map_or(30., |s| s.parse().unwrap());
     let min_word_len = matches.opt_str("m").map_or(2, |s| s.parse().unwrap());
+    let min_score = matches.opt_str("min_score").map_or(0, |s| s.parse().unwrap());
     let max_attempts = matches.opt_str("max_attempts").map_or(usize::MAX, |s| s.parse().unwrap());
+    let max_nogoods = matches.opt_str("max_nogoods")
+        .map_or(DEFAULT_MAX_NOGOODS, |s| s.parse().unwrap());
     let samples = matches.opt_str("samples").map_or(1, |s| s.parse().unwrap());
+    let threads = matches.opt_str("threads").map_or_else(num_cpus::get, |s| s.parse().unwrap());
     let verbose = matches.opt_present("v");
-    let dicts = get_dicts(match matches.opt_count("d") {
-        0 => vec!("dict/favorites.txt".to_string(), "dict/dict.txt".to_string()),
-        _ => matches.opt_strs("d"),
-    }.into_iter(), min_word_len);
-    let mut author = Author::new(&Crosswords::new(width, height), &dicts)
-        .with_min_crossing(min_crossing, min_crossing_rel)
-        .with_verbosity(verbose)
-        .with_max_attempts(max_attempts);
-    let (mut best_cw, mut best_val) = (None, i32::MIN);
-    for i in 0..samples {
-        if let Some(cw) = author.complete_cw() {
-            let val = evaluate(&cw, &author);
-            if samples > 1 {
-                println!("Solution {} of {}:", i + 1, samples);
-                print_cw(&cw, &author);
+    let stats_format = matches.opt_str("stats");
+    match stats_format.as_ref().map(|s| &s[..]) {
+        None | Some("text") | Some("json") => (),
+        Some(s) => panic!("Unknown statistics format: {}", s),
+    }
+    let seed = matches.opt_str("seed").map_or_else(|| rand::thread_rng().gen(),
+                                                   |s| s.parse().unwrap());
+    println!("Seed: {}", seed);
+    let default_normalization = normalization_at(&matches, usize::MAX);
+    let default_phrases = phrases_at(&matches, usize::MAX);
+    let mut dict_filenames: Vec<(String, Normalization, bool)> = match matches.opt_count("d") {
+        0 => vec!(("dict/favorites.txt".to_string(), default_normalization, default_phrases),
+                  ("dict/dict.txt".to_string(), default_normalization, default_phrases)),
+        _ => matches.opt_strs_pos("d").into_iter().map(|(pos, filename)| {
+            (filename, normalization_at(&matches, pos), phrases_at(&matches, pos))
+        }).collect(),
+    };
+    if let Some(dump_filename) = matches.opt_str("index_dump") {
+        let output = matches.opt_str("index_output").unwrap_or("clues.tsv".to_string());
+        let count = index_dump(&matches, &dump_filename, &dict_filenames, &output).unwrap();
+        println!("Wrote {} hints to {}.", count, output);
+        return;
+    }
+    let excluded = get_excluded_words(&matches).unwrap();
+    let required_words: HashSet<CVec> = match matches.opt_strs_pos("require").pop() {
+        None => HashSet::new(),
+        Some((pos, filename)) => {
+            let normalization = normalization_at(&matches, pos);
+            let phrases = phrases_at(&matches, pos);
+            let file = BufReader::new(File::open(&filename).unwrap());
+            // Try the required words first.
+            dict_filenames.insert(0, (filename, normalization, phrases));
+            file.lines().filter_map(Result::ok).filter_map(|line| {
+                let word = dict::split_score(&line).0.to_string();
+                if phrases {
+                    normalization.normalize_phrase(word).map(|(word, _)| word)
+                } else {
+                    normalization.normalize(word)
+                }
+            }).collect()
+        }
+    };
+    let blocked_required: Vec<String> = required_words.intersection(&excluded)
+        .map(|word| word.iter().cloned().collect()).collect();
+    if !blocked_required.is_empty() {
+        println!("Excluded required words: {}", blocked_required.join(", "));
+    }
+    let required_words: HashSet<CVec> = required_words.difference(&excluded).cloned().collect();
+    if let Some(filename) = matches.opt_str("compile") {
+        compile_dicts(&filename, &dict_filenames, min_word_len, min_score).unwrap();
+        println!("Compiled the dictionaries to {}.", filename);
+        return;
+    }
+    let mut clue_chain = create_clue_chain(&matches).unwrap();
+    let compiled = match matches.opt_str("compiled") {
+        None => None,
+        Some(filename) => {
+            let result = load_compiled(&filename, &dict_filenames, min_word_len, min_score, seed)
+                .unwrap();
+            if result.is_none() {
+                println!("{} doesn't match the dictionaries; reading them instead.", filename);
+            }
+            result
+        }
+    };
+    let (mut dicts, spellings) = compiled.unwrap_or_else(|| {
+        let mut spellings = Spellings::new();
+        let dicts = get_dicts(dict_filenames.into_iter(), min_word_len, min_score, seed,
+                              &mut spellings);
+        (dicts, spellings)
+    });
+    if !excluded.is_empty() {
+        let count: usize = dicts.iter_mut().map(|dict| dict.exclude(&excluded)).sum();
+        println!("Excluded {} words from the dictionaries.", count);
+    }
+    // Required words that are too short or have too low a score were filtered out, too.
+    let (required_words, filtered_required): (HashSet<CVec>, HashSet<CVec>) = required_words
+        .into_iter().partition(|word| dicts.iter().any(|dict| dict.contains(word)));
+    if !filtered_required.is_empty() {
+        let mut words: Vec<String> = filtered_required.iter()
+            .map(|word| word.iter().cloned().collect()).collect();
+        words.sort();
+        println!("Required words below the minimum length or score: {}", words.join(", "));
+    }
+    let dicts = Arc::new(dicts);
+    let cancel_flag = Arc::new(AtomicBool::new(false));
+    {
+        let cancel_flag = cancel_flag.clone();
+        if let Err(err) = ctrlc::set_handler(move || cancel_flag.store(true, Ordering::Relaxed)) {
+            println!("Failed to set Ctrl-C handler: {}", err);
+        }
+    }
+    let template = match matches.opt_str("template") {
+        None => template::Template { cw: Crosswords::new(width, height), bars: Vec::new() },
+        Some(filename) => template::read_template(BufReader::new(File::open(filename).unwrap()))
+            .unwrap(),
+    };
+    let config = AuthorConfig {
+        template: template,
+        required_words: required_words,
+        clue_cells: matches.opt_present("arrowword"),
+        symmetry: match matches.opt_str("blocks").as_ref().map(|s| &s[..]) {
+            None => None,
+            Some("rotational") => Some(Symmetry::Rotational),
+            Some("mirror") => Some(Symmetry::Mirror),
+            Some(s) => panic!("Unknown symmetry: {}", s),
+        },
+        min_crossing: min_crossing,
+        min_crossing_rel: min_crossing_rel,
+        max_attempts: max_attempts,
+        max_nogoods: max_nogoods,
+        verbose: verbose,
+        profiling: matches.opt_present("check_estimates"),
+        deadline: matches.opt_str("t")
+            .map(|s| Instant::now() + Duration::from_secs(s.parse().unwrap())),
+        cancel_flag: cancel_flag,
+    };
+    let (mut best_cw, mut best_val, mut best_i) = (None, i32::MIN, 0);
+    let mut best_partial: Option<Crosswords> = None;
+    let mut search_stats = SearchStats::default();
+    for (i, status, cw, sample_stats) in spawn_searches(Arc::new(config), dicts.clone(), samples,
+                                                        threads, seed) {
+        search_stats.add(&sample_stats);
+        match status {
+            SearchStatus::Complete => {
+                let val = evaluate(&cw, &dicts);
+                if samples > 1 {
+                    println!("Solution {} of {}:", i + 1, samples);
+                    print_cw(&cw, &dicts);
+                }
+                // The results arrive in any order, so prefer lower indices for reproducibility.
+                if val > best_val || (val == best_val && i < best_i) {
+                    best_cw = Some(cw);
+                    best_val = val;
+                    best_i = i;
+                }
             }
-            if val > best_val {
-                best_cw = Some(cw);
-                best_val = val;
+            status => {
+                println!("Search {} of {} stopped: {}", i + 1, samples, match status {
+                    SearchStatus::Exhausted => "no more solutions",
+                    SearchStatus::TimeUp => "time limit reached",
+                    SearchStatus::Cancelled => "interrupted",
+                    SearchStatus::Complete => unreachable!(),
+                });
+                if best_partial.iter().all(|p| cw.count_letters() > p.count_letters()) {
+                    best_partial = Some(cw);
+                }
             }
-            author.pop_to_n_words(1);
         }
     }
+    match stats_format.as_ref().map(|s| &s[..]) {
+        Some("json") => writeln!(stderr(), "{}", search_stats.to_json().pretty()).unwrap(),
+        Some(_) => write!(stderr(), "{}", search_stats).unwrap(),
+        None => (),
+    }
+    let complete = best_cw.is_some();
+    if !complete && best_partial.is_some() {
+        println!("No complete grid found. Best partial grid:");
+        best_cw = best_partial;
+    }
     if let Some(cw) = best_cw {
         if samples > 1 {
             println!("Best candidate:");
         }
-        print_cw(&cw, &author);
-        let hint_text = match matches.opt_str("wikipedia") {
-            None => HashMap::new(),
-            Some(lang) => {
-                let word_iter = cw.get_words().iter().map(|cvec| cvec.iter().cloned().collect());
-                get_hints(word_iter, lang)
+        print_cw(&cw, &dicts);
+        // Look up the hints using the original spellings, if known, e. g. Übermut for UEBERMUT.
+        let words: Vec<(String, Vec<String>)> = cw.get_words().iter().map(|cvec| {
+            let word: String = cvec.iter().cloned().collect();
+            let mut word_spellings = spellings.get(cvec).to_vec();
+            if word_spellings.is_empty() {
+                word_spellings.push(word.clone());
             }
-        };
-        write_html_to_file("puzzle.html", &cw, false, &hint_text).unwrap();
-        write_html_to_file("solution.html", &cw, true, &hint_text).unwrap();
+            (word, word_spellings)
+        }).collect();
+        let lang = hint_lang(&matches);
+        let mut hint_text = clues::get_clues_for_spellings(&mut clue_chain,
+                                                           words.iter().cloned(), &lang);
+        let missing: Vec<String> = words.into_iter()
+            .filter(|&(ref word, _)| !hint_text.contains_key(word))
+            .map(|(_, word_spellings)| word_spellings[0].clone())
+            .collect();
+        if !clue_chain.is_empty() && !missing.is_empty() {
+            println!("No hints found for: {}", missing.join(", "));
+        }
+        add_enumerations(&mut hint_text, &cw, &spellings);
+        write_files(&matches, &cw, &hint_text, &spellings);
+        // A partial grid won't be published, so its words can be used again.
+        if let (true, Some(filename)) = (complete, matches.opt_str("recent")) {
+            recent::append_words(filename, cw.get_words().iter()).unwrap();
+        }
     }
 }
@@ -0,0 +1,252 @@
+//! Reading and writing crosswords in the Across Lite `.puz` format.
+//!
+//! The format is block-based: every cell either contains a letter or is a black square, and each
+//! maximal horizontal or vertical sequence of at least two letters is a word. A `Crosswords` grid
+//! can only be converted if its borders are implied by that, i. e. if no border separates two
+//! adjacent letters.
+use cw::{BLOCK, Crosswords, CVec, Dir, Point};
+use std::ascii::AsciiExt;
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind, Read, Result, Write};
+
+const MAGIC: &'static [u8] = b"ACROSS&DOWN\0";
+const VERSION: &'static [u8] = b"1.3\0";
+const HEADER_LEN: usize = 0x34;
+const PUZ_BLOCK: u8 = b'.';
+const PUZ_EMPTY: u8 = b'-';
+const TITLE: &'static str = "Crosswords";
+
+fn invalid_data(msg: String) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+/// Computes the checksum of the given bytes, starting with the given value.
+fn checksum(data: &[u8], init: u16) -> u16 {
+    data.iter().fold(init, |cksum, &b| {
+        let rotated = if cksum & 1 == 1 { (cksum >> 1) + 0x8000 } else { cksum >> 1 };
+        rotated.wrapping_add(b as u16)
+    })
+}
+
+/// Computes the checksum of the strings section: the title, author, copyright and notes including
+/// their terminating zeros (if they are not empty), and the clues without them.
+fn strings_checksum(strings: &[Vec<u8>], clue_count: usize, init: u16) -> u16 {
+    let mut cksum = init;
+    for (i, string) in strings.iter().enumerate() {
+        let is_clue = i >= 3 && i < 3 + clue_count;
+        if is_clue {
+            cksum = checksum(string, cksum);
+        } else if !string.is_empty() {
+            cksum = checksum(string, cksum);
+            cksum = checksum(&[0], cksum);
+        }
+    }
+    cksum
+}
+
+/// Encodes the text as ISO-8859-1, replacing unsupported characters with question marks.
+fn encode(text: &str) -> Vec<u8> {
+    text.chars().map(|c| if (c as u32) < 256 { c as u8 } else { b'?' }).collect()
+}
+
+fn decode(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| b as char).collect()
+}
+
+fn put_u16(bytes: &mut [u8], pos: usize, value: u16) {
+    bytes[pos] = (value & 0xFF) as u8;
+    bytes[pos + 1] = (value >> 8) as u8;
+}
+
+fn get_u16(bytes: &[u8], pos: usize) -> u16 {
+    bytes[pos] as u16 | ((bytes[pos + 1] as u16) << 8)
+}
+
+/// Returns an error if the grid's borders are not equivalent to a block layout.
+fn check_blocks(cw: &Crosswords) -> Result<()> {
+    if cw.get_width() > 255 || cw.get_height() > 255 {
+        return Err(Error::new(ErrorKind::InvalidInput,
+                              "grids larger than 255x255 cannot be saved as .puz"));
+    }
+    for y in 0..(cw.get_height() as i32) {
+        for x in 0..(cw.get_width() as i32) {
+            let p = Point::new(x, y);
+            for &dir in &[Dir::Right, Dir::Down] {
+                if cw.is_letter(p) && cw.is_letter(p + dir.point()) && cw.get_border(p, dir) {
+                    return Err(Error::new(ErrorKind::InvalidInput, format!(
+                        "the border after ({}, {}) {:?} separates two letters, which cannot be \
+                         represented with blocks", x, y, dir)));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Write the crosswords and the given hints to the given writer in the `.puz` format. Words
+/// without a hint get their solution in brackets as the clue. Returns an error if the grid
+/// cannot be represented with blocks or contains letters that are not in ISO-8859-1.
+pub fn write_puz<T: Write>(writer: &mut T, cw: &Crosswords, hint_text: &HashMap<String, String>)
+        -> Result<()> {
+    try!(check_blocks(cw));
+    let (width, height) = (cw.get_width(), cw.get_height());
+    let cells: Vec<Option<char>> = (0..height).flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| Point::new(x as i32, y as i32))
+        .map(|p| if cw.is_letter(p) { cw.get_char(p) } else { None }).collect();
+    if let Some(c) = cells.iter().filter_map(|&c| c).find(|&c| c as u32 > 0xFF) {
+        return Err(Error::new(ErrorKind::InvalidInput, format!(
+            "the letter {} is not in ISO-8859-1 and cannot be saved as .puz", c)));
+    }
+    let solution: Vec<u8> = cells.iter().map(|c| c.map_or(PUZ_BLOCK, |c| c as u8)).collect();
+    let grid: Vec<u8> = cells.iter().map(|c| c.map_or(PUZ_BLOCK, |_| PUZ_EMPTY)).collect();
+    let clues: Vec<Vec<u8>> = cw.numbered_word_ranges().into_iter().map(|(_, range)| {
+        let word: String = cw.chars(range).collect();
+        encode(&hint_text.get(&word).cloned().unwrap_or(format!("[{}]", word)))
+    }).collect();
+    let mut strings = vec!(encode(TITLE), Vec::new(), Vec::new());
+    let clue_count = clues.len();
+    strings.extend(clues.into_iter());
+    strings.push(Vec::new()); // Notes.
+
+    let mut header = vec!(0; HEADER_LEN);
+    header[0x02..0x0E].clone_from_slice(MAGIC);
+    header[0x18..0x1C].clone_from_slice(VERSION);
+    header[0x2C] = width as u8;
+    header[0x2D] = height as u8;
+    put_u16(&mut header, 0x2E, clue_count as u16);
+    put_u16(&mut header, 0x30, 1);
+    let cib_cksum = checksum(&header[0x2C..HEADER_LEN], 0);
+    put_u16(&mut header, 0x0E, cib_cksum);
+    let mut cksum = checksum(&solution, cib_cksum);
+    cksum = checksum(&grid, cksum);
+    put_u16(&mut header, 0x00, strings_checksum(&strings, clue_count, cksum));
+    let partial_cksums = [cib_cksum,
+                          checksum(&solution, 0),
+                          checksum(&grid, 0),
+                          strings_checksum(&strings, clue_count, 0)];
+    for (i, (&lo, &hi)) in b"ICHE".iter().zip(b"ATED".iter()).enumerate() {
+        header[0x10 + i] = lo ^ (partial_cksums[i] & 0xFF) as u8;
+        header[0x14 + i] = hi ^ (partial_cksums[i] >> 8) as u8;
+    }
+
+    try!(writer.write_all(&header));
+    try!(writer.write_all(&solution));
+    try!(writer.write_all(&grid));
+    for string in strings {
+        try!(writer.write_all(&string));
+        try!(writer.write_all(&[0]));
+    }
+    Ok(())
+}
+
+/// Read a crosswords grid and its hints from the given reader in the `.puz` format. The hints are
+/// returned as a map from each word to its clue.
+pub fn read_puz<T: Read>(reader: &mut T) -> Result<(Crosswords, HashMap<String, String>)> {
+    let mut bytes = Vec::new();
+    try!(reader.read_to_end(&mut bytes));
+    if bytes.len() < HEADER_LEN || &bytes[0x02..0x0E] != MAGIC {
+        return Err(invalid_data("not an Across Lite file".to_string()));
+    }
+    let (width, height) = (bytes[0x2C] as usize, bytes[0x2D] as usize);
+    let clue_count = get_u16(&bytes, 0x2E) as usize;
+    if get_u16(&bytes, 0x32) != 0 {
+        return Err(invalid_data("scrambled puzzles are not supported".to_string()));
+    }
+    if width < 2 || height < 2 || bytes.len() < HEADER_LEN + 2 * width * height {
+        return Err(invalid_data("invalid grid size".to_string()));
+    }
+    let solution = &bytes[HEADER_LEN..(HEADER_LEN + width * height)];
+    let grid = &bytes[(HEADER_LEN + width * height)..(HEADER_LEN + 2 * width * height)];
+    let mut strings: Vec<Vec<u8>> = bytes[(HEADER_LEN + 2 * width * height)..]
+        .split(|&b| b == 0).map(|s| s.to_vec()).take(4 + clue_count).collect();
+    if strings.len() < 3 + clue_count {
+        return Err(invalid_data("missing clues".to_string()));
+    }
+    strings.resize(4 + clue_count, Vec::new());
+    let cib_cksum = checksum(&bytes[0x2C..HEADER_LEN], 0);
+    let mut cksum = checksum(solution, cib_cksum);
+    cksum = checksum(grid, cksum);
+    if get_u16(&bytes, 0x0E) != cib_cksum
+            || get_u16(&bytes, 0x00) != strings_checksum(&strings, clue_count, cksum) {
+        return Err(invalid_data("checksum mismatch".to_string()));
+    }
+
+    let chars: CVec = solution.iter().map(|&b| match b {
+        PUZ_BLOCK => BLOCK,
+        b => (b as char).to_ascii_uppercase(),
+    }).collect();
+    // Adjacent letters always belong to the same word.
+    let is_letter = |x: usize, y: usize| chars[x + width * y] != BLOCK;
+    let right_border = (0..height).flat_map(|y| (0..(width - 1)).map(move |x| (x, y)))
+        .map(|(x, y)| !is_letter(x, y) || !is_letter(x + 1, y)).collect();
+    let down_border = (0..(height - 1)).flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| !is_letter(x, y) || !is_letter(x, y + 1)).collect();
+    let cw = try!(Crosswords::from_cells(width, height, chars.clone(), right_border, down_border)
+                  .map_err(invalid_data));
+    let ranges = cw.numbered_word_ranges();
+    if ranges.len() != clue_count {
+        return Err(invalid_data(format!("expected {} clues, found {}", ranges.len(), clue_count)));
+    }
+    let mut hint_text = HashMap::new();
+    for ((_, range), clue) in ranges.into_iter().zip(strings[3..].iter()) {
+        let word: String = cw.chars(range).collect();
+        let clue = decode(clue);
+        if clue != format!("[{}]", word) {
+            hint_text.insert(word, clue);
+        }
+    }
+    Ok((cw, hint_text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cw::{Crosswords, Dir, Point};
+    use std::collections::HashMap;
+    use std::io::ErrorKind;
+
+    #[test]
+    fn test_checksum() {
+        assert_eq!(16500, checksum(b"ABC", 0));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        // Create the following grid:
+        // FOO
+        // A#N
+        // BAD
+        let mut cw = Crosswords::new(3, 3);
+        cw.try_word(Point::new(0, 0), Dir::Right, &"FOO".chars().collect());
+        cw.try_word(Point::new(0, 2), Dir::Right, &"BAD".chars().collect());
+        cw.try_word(Point::new(0, 0), Dir::Down, &"FAB".chars().collect());
+        cw.try_word(Point::new(2, 0), Dir::Down, &"OND".chars().collect());
+        let mut hint_text = HashMap::new();
+        hint_text.insert("FOO".to_string(), "Placeholder name".to_string());
+        hint_text.insert("BAD".to_string(), "Not good".to_string());
+        let mut bytes = Vec::new();
+        write_puz(&mut bytes, &cw, &hint_text).unwrap();
+        let (read_cw, read_hint_text) = read_puz(&mut &bytes[..]).unwrap();
+        assert_eq!(cw.to_string(), read_cw.to_string());
+        assert_eq!(hint_text, read_hint_text);
+        // Corrupt one letter of the solution:
+        bytes[HEADER_LEN] = b'G';
+        assert!(read_puz(&mut &bytes[..]).is_err());
+    }
+
+    #[test]
+    fn test_borders_between_letters() {
+        let mut cw = Crosswords::new(2, 2);
+        cw.try_word(Point::new(0, 0), Dir::Right, &"AB".chars().collect());
+        cw.try_word(Point::new(0, 1), Dir::Right, &"CD".chars().collect());
+        assert!(write_puz(&mut Vec::new(), &cw, &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_non_latin1_letters() {
+        let mut cw = Crosswords::new(2, 1);
+        cw.try_word(Point::new(0, 0), Dir::Right, &"ŁA".chars().collect());
+        let err = write_puz(&mut Vec::new(), &cw, &HashMap::new()).unwrap_err();
+        assert_eq!(ErrorKind::InvalidInput, err.kind());
+    }
+}
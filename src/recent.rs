@@ -0,0 +1,70 @@
+//! A log of the words used in previously generated puzzles, so that they are not repeated too soon.
+//!
+//! The log is a text file with one line per puzzle, containing its words separated by spaces, with
+//! the most recent puzzle last:
+//!
+//! ```text
+//! FOO BAR BAZ
+//! RUST SERVO
+//! ```
+use cw::CVec;
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, ErrorKind, Result, Write};
+use std::path::Path;
+
+/// Returns the words used in the last `count` puzzles in the log. If the log doesn't exist yet,
+/// the set is empty.
+pub fn read_recent_words<P: AsRef<Path>>(filename: P, count: usize) -> Result<HashSet<CVec>> {
+    let file = match File::open(filename) {
+        Ok(file) => file,
+        Err(ref err) if err.kind() == ErrorKind::NotFound => return Ok(HashSet::new()),
+        Err(err) => return Err(err),
+    };
+    let mut lines = Vec::new();
+    for line in BufReader::new(file).lines() {
+        lines.push(try!(line));
+    }
+    let start = if lines.len() > count { lines.len() - count } else { 0 };
+    Ok(lines[start..].iter()
+        .flat_map(|line| line.split_whitespace())
+        .map(|word| word.chars().collect())
+        .collect())
+}
+
+/// Appends a line with the given words to the log, creating it if necessary.
+pub fn append_words<'a, P: AsRef<Path>, T: Iterator<Item = &'a CVec>>(filename: P, words: T)
+        -> Result<()> {
+    let mut words: Vec<String> = words.map(|word| word.iter().cloned().collect()).collect();
+    words.sort();
+    let mut file = try!(OpenOptions::new().create(true).append(true).open(filename));
+    writeln!(file, "{}", words.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cw::CVec;
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    #[test]
+    fn test_recent_words() {
+        let filename = env::temp_dir()
+            .join(format!("crosswords-rs-test-recent-{}.txt", process::id()));
+        let _ = fs::remove_file(&filename);
+        assert!(read_recent_words(&filename, 2).unwrap().is_empty());
+        let to_cvecs = |words: &[&str]| -> Vec<CVec> {
+            words.iter().map(|word| word.chars().collect()).collect()
+        };
+        append_words(&filename, to_cvecs(&["FOO", "BAR"]).iter()).unwrap();
+        append_words(&filename, to_cvecs(&["BAZ"]).iter()).unwrap();
+        append_words(&filename, to_cvecs(&["QUX", "FOO"]).iter()).unwrap();
+        let recent = read_recent_words(&filename, 2).unwrap();
+        let expected: HashSet<CVec> = to_cvecs(&["BAZ", "QUX", "FOO"]).into_iter().collect();
+        assert_eq!(expected, recent);
+        assert!(read_recent_words(&filename, 0).unwrap().is_empty());
+        fs::remove_file(&filename).unwrap();
+    }
+}
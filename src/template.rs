@@ -0,0 +1,122 @@
+//! Reading partially filled crosswords grids from text files.
+//!
+//! Each row of the grid is given as a line of cells, separated by a space or by `|`, which
+//! denotes a bar between the two cells. A cell is either a letter, `#` for a free cell, or `*`
+//! for a cell that must stay empty. Between two rows, there can be a line with a `-` below each
+//! cell that has a bar at its bottom. E. g.:
+//!
+//! ```text
+//! F O O|# #
+//!   -
+//! A # # # *
+//! B # # # #
+//! ```
+//!
+//! Adjacent letters that are not separated by a bar belong to the same word, so the template
+//! above contains the words `FOO` and `FAB`.
+use cw::{BLOCK, Crosswords, CVec, Dir, Point, SOLID};
+use std::io::{BufRead, Error, ErrorKind, Result};
+
+const BAR_RIGHT: char = '|';
+const BAR_DOWN: char = '-';
+
+/// A grid containing the pinned words and the cells that must stay empty, and a list of bars
+/// that no word may cross.
+pub struct Template {
+    pub cw: Crosswords,
+    /// Each bar is given as a cell and a direction: It separates the cell from its right resp.
+    /// bottom neighbor.
+    pub bars: Vec<(Point, Dir)>,
+}
+
+fn invalid_data(msg: String) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+/// Read a template from the given reader.
+pub fn read_template<T: BufRead>(reader: T) -> Result<Template> {
+    let mut chars = CVec::new();
+    let mut right_bars = Vec::new();
+    let mut down_bars = Vec::new();
+    let mut width = None;
+    let mut height = 0;
+    for line in reader.lines() {
+        let line: CVec = try!(line).trim_right().chars().collect();
+        if line.is_empty() {
+            continue;
+        }
+        if line.iter().all(|&c| c == BAR_DOWN || c == ' ') {
+            if height == 0 {
+                return Err(invalid_data("the first line must be a row of cells".to_string()));
+            }
+            for (x, _) in line.iter().enumerate().filter(|&(_, &c)| c == BAR_DOWN) {
+                down_bars.push(Point::new(x as i32 / 2, height - 1));
+            }
+            continue;
+        }
+        let row_width = (line.len() + 1) / 2;
+        if *width.get_or_insert(row_width) != row_width {
+            return Err(invalid_data(format!("row {} has the wrong length", height + 1)));
+        }
+        for (x, &c) in line.iter().enumerate() {
+            if x % 2 == 1 {
+                match c {
+                    BAR_RIGHT => right_bars.push(Point::new(x as i32 / 2, height)),
+                    ' ' => (),
+                    _ => return Err(invalid_data(format!("invalid separator '{}'", c))),
+                }
+            } else if c == BLOCK || c == SOLID {
+                chars.push(c);
+            } else if c.is_alphabetic() {
+                chars.push(c.to_uppercase().next().unwrap());
+            } else {
+                return Err(invalid_data(format!("invalid cell '{}'", c)));
+            }
+        }
+        height += 1;
+    }
+    let width = width.unwrap_or(0);
+    let height = height as usize;
+    if width < 2 || height < 2 {
+        return Err(invalid_data("the grid must be at least 2x2".to_string()));
+    }
+    let is_letter = |p: Point| chars[p.coord(width, height).unwrap()].is_alphabetic();
+    // Adjacent letters belong to the same word unless they are separated by a bar.
+    let right_border = (0..height).flat_map(|y| (0..(width - 1)).map(move |x| (x, y)))
+        .map(|(x, y)| Point::new(x as i32, y as i32))
+        .map(|p| !is_letter(p) || !is_letter(p + Point::new(1, 0)) || right_bars.contains(&p))
+        .collect();
+    let down_border = (0..(height - 1)).flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| Point::new(x as i32, y as i32))
+        .map(|p| !is_letter(p) || !is_letter(p + Point::new(0, 1)) || down_bars.contains(&p))
+        .collect();
+    let cw = try!(Crosswords::from_cells(width, height, chars.clone(), right_border, down_border)
+                  .map_err(invalid_data));
+    let bars = right_bars.into_iter().map(|p| (p, Dir::Right))
+        .chain(down_bars.into_iter().map(|p| (p, Dir::Down)))
+        .collect();
+    Ok(Template { cw: cw, bars: bars })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cw::{Dir, Point};
+
+    #[test]
+    fn test_read_template() {
+        let text = "F O O|# #\n  -\nA # # # *\nB # # # #\n";
+        let template = read_template(text.as_bytes()).unwrap();
+        assert_eq!(5, template.cw.get_width());
+        assert_eq!(3, template.cw.get_height());
+        let mut words: Vec<String> = template.cw.get_words().iter()
+            .map(|word| word.iter().cloned().collect()).collect();
+        words.sort();
+        assert_eq!(vec!("FAB".to_string(), "FOO".to_string()), words);
+        assert!(template.cw.is_solid(Point::new(4, 1)));
+        assert_eq!(vec!((Point::new(2, 0), Dir::Right), (Point::new(1, 0), Dir::Down)),
+                   template.bars);
+        assert!(read_template("A # #\n# # #\n".as_bytes()).is_err()); // The A is isolated.
+        assert!(read_template("# # #\n# #\n".as_bytes()).is_err());
+    }
+}
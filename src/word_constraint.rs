@@ -1,4 +1,6 @@
+use compiled::{read_chars, read_u32, write_chars, write_u32};
 use cw::CVec;
+use std::io::{Read, Result, Write};
 use std::iter;
 use std::slice;
 use std::ops;
@@ -6,7 +8,7 @@ use std::option;
 
 /// A `WordConstraint` represents the subset of all words with a given length, and optionally with
 /// a given n-gram at a specific position.
-#[derive(Clone, Hash, Eq, PartialEq)]
+#[derive(Clone, Hash, Eq, Ord, PartialEq, PartialOrd)]
 pub enum WordConstraint {
     /// All words with the given length.
     Length(usize),
@@ -60,6 +62,45 @@ impl WordConstraint {
         iter::repeat(word).zip(1..(max_n + 1)).flat_map(to_iter)
     }
 
+    /// Returns the n-gram, its position and the word length.
+    fn parts(&self) -> (CVec, usize, usize) {
+        match *self {
+            WordConstraint::Length(len) => (Vec::new(), 0, len),
+            WordConstraint::CharAt(c, pos, len) => (vec!(c), pos, len),
+            WordConstraint::BigramAt(ngram, pos, len) => (ngram.to_vec(), pos, len),
+            WordConstraint::TrigramAt(ngram, pos, len) => (ngram.to_vec(), pos, len),
+            WordConstraint::NGramAt(ref ngram, pos, len) => (ngram.clone(), pos, len),
+        }
+    }
+
+    /// Returns the number of letters the constraint specifies, or 0 for `Length`.
+    pub fn ngram_len(&self) -> usize {
+        match *self {
+            WordConstraint::Length(_) => 0,
+            WordConstraint::CharAt(..) => 1,
+            WordConstraint::BigramAt(..) => 2,
+            WordConstraint::TrigramAt(..) => 3,
+            WordConstraint::NGramAt(ref ngram, _, _) => ngram.len(),
+        }
+    }
+
+    /// Write the constraint in the compiled dictionary format: the n-gram, its position and the
+    /// word length.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<()> {
+        let (ngram, pos, len) = self.parts();
+        try!(write_chars(writer, &ngram));
+        try!(write_u32(writer, pos as u32));
+        write_u32(writer, len as u32)
+    }
+
+    /// Read a constraint in the compiled dictionary format.
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<WordConstraint> {
+        let ngram = try!(read_chars(reader));
+        let pos = try!(read_u32(reader)) as usize;
+        let len = try!(read_u32(reader)) as usize;
+        Ok(WordConstraint::with_ngram(&ngram, pos, len))
+    }
+
     /// Return an iterator over all constraints applying to a given word.
     pub fn all<'a>(word: &'a CVec, max_n: usize) -> AllConstraintsIter<'a> {
         WordConstraint::all_ngram_constraints(word, max_n)
@@ -1,4 +1,5 @@
 use cw::{BLOCK, CVec};
+use dict::Dict;
 use std::cmp;
 use std::collections::HashMap;
 use std::usize;
@@ -25,12 +26,26 @@ impl WordStats {
     }
 
     /// Add all words in the iterator.
+    #[cfg(test)]
     pub fn add_words<'a, T: Iterator<Item = &'a CVec>>(&mut self, words: T) {
         for word in words {
             self.add_word(word);
         }
     }
 
+    /// Add all words in the dictionary, using its index instead of going through the words.
+    pub fn add_dict(&mut self, dict: &Dict) {
+        for (wc, list) in dict.constraint_lists() {
+            if list.is_empty() || wc.ngram_len() > self.max_n {
+                continue;
+            }
+            if let WordConstraint::Length(len) = *wc {
+                self.min_len = cmp::min(self.min_len, len);
+            }
+            *self.freq.entry(wc.clone()).or_insert(0) += list.len();
+        }
+    }
+
     fn get(&self, wc: &WordConstraint) -> usize {
         *self.freq.get(wc).unwrap_or(&0)
     }
@@ -48,12 +63,14 @@ impl WordStats {
         self.get(&WordConstraint::with_ngram(ngram, pos, len))
     }
 
+    #[cfg(test)]
     fn increase(&mut self, wc: WordConstraint) {
         let prev_freq = self.get(&wc);
         self.freq.insert(wc, prev_freq + 1);
     }
 
     /// Increase the word count for each `WordConstraint` matching the given word.
+    #[cfg(test)]
     pub fn add_word(&mut self, word: &CVec) {
         self.min_len = cmp::min(self.min_len, word.len());
         for wc in WordConstraint::all(word, self.max_n) {
@@ -109,6 +126,8 @@ impl WordStats {
 mod tests {
     use super::*;
     use cw::CVec;
+    use dict::Dict;
+    use rand::thread_rng;
     use std::collections::HashSet;
 
     #[test]
@@ -129,4 +148,17 @@ mod tests {
         assert_eq!(1., ws.estimate_matches(&"ABC#".chars().collect()));
         assert_eq!(0., ws.estimate_matches(&"#C##".chars().collect()));
     }
+
+    #[test]
+    fn test_add_dict() {
+        let words = Dict::to_cvec_set(vec!("ABCD", "AXYZ", "FOO").into_iter()
+            .map(|s| s.to_string()));
+        let dict = Dict::new(words.iter(), &mut thread_rng());
+        let mut ws0 = WordStats::new(2);
+        ws0.add_words(words.iter());
+        let mut ws1 = WordStats::new(2);
+        ws1.add_dict(&dict);
+        assert!(ws0.freq == ws1.freq);
+        assert_eq!(3, ws1.get_min_len());
+    }
 }